@@ -1,52 +1,114 @@
 use std::fs::File;
-use std::io::{self, BufRead};
+use std::io::{self, BufRead, Write};
 
+/// A byte in a bank that is not a decimal digit.
 #[derive(Debug)]
-struct JoltageReader {
-    lines: io::Lines<io::BufReader<File>>,
+enum JoltageError {
+    Io(io::Error),
+    NotADigit {
+        line: usize,
+        column: usize,
+        byte: u8,
+    },
+}
+
+impl std::fmt::Display for JoltageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JoltageError::Io(e) => write!(f, "{e}"),
+            JoltageError::NotADigit { line, column, byte } => write!(
+                f,
+                "line {line}, column {column}: not a digit: {:?}",
+                char::from(*byte)
+            ),
+        }
+    }
+}
+
+impl std::error::Error for JoltageError {}
+
+impl From<io::Error> for JoltageError {
+    fn from(e: io::Error) -> Self {
+        JoltageError::Io(e)
+    }
+}
+
+/// Reads banks one line at a time into a buffer that is reused
+/// between lines.  Each bank is handed out as a borrowed slice of
+/// digit values (0-9), so `next_bank` is used in place of an
+/// `Iterator`.
+#[derive(Debug)]
+struct JoltageReader<R = io::BufReader<File>> {
+    reader: R,
+    buf: Vec<u8>,
+    line: usize,
 }
 
 impl JoltageReader {
     fn new(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
         let file = File::open(path)?;
-        let lines = io::BufReader::new(file).lines();
-        Ok(Self { lines })
+        Ok(Self::from_reader(io::BufReader::new(file)))
     }
 }
 
-impl Iterator for JoltageReader {
-    type Item = Vec<usize>;
+impl<R: BufRead> JoltageReader<R> {
+    fn from_reader(reader: R) -> Self {
+        Self {
+            reader,
+            buf: Vec::new(),
+            line: 0,
+        }
+    }
+
+    /// Returns the digits of the next non-blank line, or `None` at the
+    /// end of the input.
+    fn next_bank(&mut self) -> Option<Result<&[u8], JoltageError>> {
+        loop {
+            self.buf.clear();
+            match self.reader.read_until(b'\n', &mut self.buf) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(e) => return Some(Err(e.into())),
+            }
+            self.line += 1;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.lines.next().map(|line| {
-            let line = line.expect("Bad line in file");
-            let mut line_str = line.as_str();
-            let mut joltages = Vec::new();
-            while line_str.len() > 0 {
-                let digit;
-                (digit, line_str) = line_str.split_at(1);
-                let value = digit.parse().expect("Not an integer: {e}");
-                joltages.push(value);
+            while let Some(b'\n' | b'\r') = self.buf.last() {
+                self.buf.pop();
+            }
+            if self.buf.is_empty() {
+                continue;
+            }
+
+            for (i, byte) in self.buf.iter_mut().enumerate() {
+                if !byte.is_ascii_digit() {
+                    return Some(Err(JoltageError::NotADigit {
+                        line: self.line,
+                        column: i + 1,
+                        byte: *byte,
+                    }));
+                }
+                *byte -= b'0';
             }
-            joltages
-        })
+            return Some(Ok(&self.buf));
+        }
     }
 }
 
 fn prob1(prob_file: &str) -> Result<usize, Box<dyn std::error::Error>> {
-    let joltage_reader = JoltageReader::new(prob_file)?;
+    let mut joltage_reader = JoltageReader::new(prob_file)?;
 
     let mut total_joltage = 0;
 
-    for joltages in joltage_reader {
+    while let Some(joltages) = joltage_reader.next_bank() {
+        let joltages = joltages?;
         // println!("joltages: {joltages:?}");
         let n_joltages = joltages.len();
         let mut max_10s = (0, 0);
 
         // find the max for 10s digit -- skip the last joltage
-        for i in 0..(n_joltages - 1) {
-            if joltages[i] > max_10s.0 {
-                max_10s = (joltages[i], i);
+        for (i, &joltage) in joltages.iter().enumerate().take(n_joltages - 1) {
+            if joltage > max_10s.0 {
+                max_10s = (joltage, i);
             }
         }
         // println!("max: pos {}: {}", max_10s.1, max_10s.0);
@@ -55,12 +117,12 @@ fn prob1(prob_file: &str) -> Result<usize, Box<dyn std::error::Error>> {
         // guaranteed to have at least one more digit to the right of
         // the max 10s digit.
         let mut max_1s = 0;
-        for i in (max_10s.1 + 1)..n_joltages {
-            max_1s = std::cmp::max(max_1s, joltages[i]);
+        for &joltage in &joltages[(max_10s.1 + 1)..n_joltages] {
+            max_1s = std::cmp::max(max_1s, joltage);
         }
         // println!("max_1s: {max_1s}");
 
-        let max_joltage = max_10s.0 * 10 + max_1s;
+        let max_joltage = max_10s.0 as usize * 10 + max_1s as usize;
         // println!("Max joltage: {max_joltage}");
         total_joltage += max_joltage;
     }
@@ -68,45 +130,50 @@ fn prob1(prob_file: &str) -> Result<usize, Box<dyn std::error::Error>> {
     Ok(total_joltage)
 }
 
+/// Selects the `k` joltages from a bank that form the largest
+/// possible number, keeping their original order.  Returns the
+/// selected digits, most significant first.
+fn best_joltages(joltages: &[u8], k: usize) -> Vec<u8> {
+    let n_joltages = joltages.len();
+    assert!(
+        k <= n_joltages,
+        "cannot select {k} of {n_joltages} joltages"
+    );
+
+    let mut max_jolts = Vec::with_capacity(k);
+    let mut end = k;
+    let mut next_start = 0;
+    while end > 0 {
+        let end_range = n_joltages - (end - 1);
+
+        // find the max for current digit position -- skip the trailing joltages
+        let mut max = (0, next_start);
+        for (i, &joltage) in joltages.iter().enumerate().take(end_range).skip(next_start) {
+            if joltage > max.0 {
+                max = (joltage, i);
+            }
+        }
+        max_jolts.push(max.0);
+        next_start = max.1 + 1;
+        end -= 1;
+    }
+
+    max_jolts
+}
+
 fn prob2(prob_file: &str) -> Result<usize, Box<dyn std::error::Error>> {
-    let joltage_reader = JoltageReader::new(prob_file)?;
+    let mut joltage_reader = JoltageReader::new(prob_file)?;
 
     let mut total_joltage = 0;
 
     const N_JOLTS: usize = 12;
 
-    for joltages in joltage_reader {
-        let n_joltages = joltages.len();
-        // println!("joltages: {n_joltages}: {joltages:?}");
-
-        let mut max_jolt_pos = Vec::new();
-        let mut end = N_JOLTS;
-        let mut next_start = 0;
-        while end > 0 {
-            let end_range = n_joltages - (end - 1);
-            // println!("end: {end}");
-
-            // find the max for current digit position -- skip the trailing joltages
-            let mut max = (0, 0);
-            // println!("Checking range: {}-{}", next_start, end_range);
-            for i in next_start..end_range {
-                // println!("Checking joltage[{i}]: {}", joltages[i]);
-                if joltages[i] > max.0 {
-                    max = (joltages[i], i);
-                }
-            }
-            // println!("Found max: {max:?}");
-            max_jolt_pos.push(max);
-            next_start = max.1 + 1;
-            end -= 1;
-        }
-
-        // println!("max_jolt_pos: {max_jolt_pos:?}");
-        let value = max_jolt_pos
+    while let Some(joltages) = joltage_reader.next_bank() {
+        let value = best_joltages(joltages?, N_JOLTS)
             .into_iter()
             .enumerate()
             .fold(0, |acc, (i, val)| {
-                acc + (10usize.pow((N_JOLTS - 1 - i) as u32) * val.0)
+                acc + (10usize.pow((N_JOLTS - 1 - i) as u32) * val as usize)
             });
         // println!("max_jolt value: {value}");
         total_joltage += value;
@@ -115,11 +182,275 @@ fn prob2(prob_file: &str) -> Result<usize, Box<dyn std::error::Error>> {
     Ok(total_joltage)
 }
 
+/// Decimal joltage of arbitrary length.  A bank can hold far more
+/// batteries than the 19 digits a usize can represent.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Joltage {
+    // most significant digit first, no leading zeros
+    digits: Vec<u8>,
+}
+
+impl Joltage {
+    fn from_digits(digits: &[u8]) -> Self {
+        let leading_zeros = digits.iter().take_while(|&&d| d == 0).count();
+        Self {
+            digits: digits[leading_zeros..].to_vec(),
+        }
+    }
+}
+
+impl Ord for Joltage {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.digits
+            .len()
+            .cmp(&other.digits.len())
+            .then_with(|| self.digits.cmp(&other.digits))
+    }
+}
+
+impl PartialOrd for Joltage {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl std::ops::Add for &Joltage {
+    type Output = Joltage;
+
+    fn add(self, other: &Joltage) -> Joltage {
+        let mut digits = Vec::with_capacity(self.digits.len().max(other.digits.len()) + 1);
+        let mut lhs = self.digits.iter().rev();
+        let mut rhs = other.digits.iter().rev();
+        let mut carry = 0;
+        loop {
+            let (a, b) = (lhs.next(), rhs.next());
+            if a.is_none() && b.is_none() {
+                break;
+            }
+            let sum = a.copied().unwrap_or(0) + b.copied().unwrap_or(0) + carry;
+            digits.push(sum % 10);
+            carry = sum / 10;
+        }
+        if carry > 0 {
+            digits.push(carry);
+        }
+        digits.reverse();
+        Joltage { digits }
+    }
+}
+
+impl std::fmt::Display for Joltage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.digits.is_empty() {
+            return write!(f, "0");
+        }
+        for digit in &self.digits {
+            write!(f, "{digit}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Best joltage of a bank for every battery count: `curve[k]` is the
+/// largest value obtainable by turning on `k` batteries, with
+/// `curve[0]` being zero.
+///
+/// Rather than selecting from scratch for every k, batteries are
+/// dropped one at a time: always the first one that is smaller than
+/// its right neighbour, or the last one when none is.  Each drop
+/// leaves the best selection for one battery fewer, and the scan
+/// position only ever steps back by one, so finding the drops is
+/// linear in the bank length.
+fn joltage_curve(joltages: &[u8]) -> Vec<Joltage> {
+    const END: usize = usize::MAX;
+
+    let n_joltages = joltages.len();
+    let mut curve = vec![Joltage::default(); n_joltages + 1];
+
+    // doubly linked list over the batteries still turned on
+    let mut next: Vec<usize> = (1..=n_joltages).collect();
+    let mut prev: Vec<usize> = (0..n_joltages).map(|i| i.wrapping_sub(1)).collect();
+    if let Some(last) = next.last_mut() {
+        *last = END;
+    }
+    let mut head = if n_joltages > 0 { 0 } else { END };
+
+    let mut pos = head;
+    let mut digits = Vec::with_capacity(n_joltages);
+    for k in (1..=n_joltages).rev() {
+        digits.clear();
+        let mut i = head;
+        while i != END {
+            digits.push(joltages[i]);
+            i = next[i];
+        }
+        curve[k] = Joltage::from_digits(&digits);
+
+        // everything before pos is non-increasing, so look from there
+        while next[pos] != END && joltages[pos] >= joltages[next[pos]] {
+            pos = next[pos];
+        }
+
+        let (before, after) = (prev[pos], next[pos]);
+        if before == END {
+            head = after;
+        } else {
+            next[before] = after;
+        }
+        if after != END {
+            prev[after] = before;
+        }
+        pos = if before == END { after } else { before };
+    }
+
+    curve
+}
+
+fn joltage_curves(prob_file: &str) -> Result<Vec<Vec<Joltage>>, Box<dyn std::error::Error>> {
+    let mut joltage_reader = JoltageReader::new(prob_file)?;
+    let mut curves = Vec::new();
+    while let Some(joltages) = joltage_reader.next_bank() {
+        curves.push(joltage_curve(joltages?));
+    }
+    Ok(curves)
+}
+
+/// Writes one `bank,k,joltage` row per bank and battery count.
+fn write_curves_csv(out: &mut impl Write, curves: &[Vec<Joltage>]) -> io::Result<()> {
+    writeln!(out, "bank,k,joltage")?;
+    for (bank, curve) in curves.iter().enumerate() {
+        for (k, joltage) in curve.iter().enumerate().skip(1) {
+            writeln!(out, "{},{k},{joltage}", bank + 1)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the curves as a JSON array with one object per bank.  The
+/// joltages are strings as they overflow JSON's double precision
+/// numbers.
+fn write_curves_json(out: &mut impl Write, curves: &[Vec<Joltage>]) -> io::Result<()> {
+    writeln!(out, "[")?;
+    for (bank, curve) in curves.iter().enumerate() {
+        let values: Vec<String> = curve
+            .iter()
+            .skip(1)
+            .map(|joltage| format!("\"{joltage}\""))
+            .collect();
+        let separator = if bank + 1 < curves.len() { "," } else { "" };
+        writeln!(
+            out,
+            "  {{\"bank\": {}, \"curve\": [{}]}}{separator}",
+            bank + 1,
+            values.join(", ")
+        )?;
+    }
+    writeln!(out, "]")
+}
+
+/// How a global battery budget is spread across the banks.
+#[derive(Debug)]
+struct Allocation {
+    // batteries turned on in each bank, in file order
+    counts: Vec<usize>,
+    total: Joltage,
+}
+
+/// Distributes `budget` batteries over the banks described by
+/// `curves` so that the summed joltage is as large as possible.
+/// Returns `None` when the banks cannot hold that many batteries.
+fn allocate_batteries(curves: &[Vec<Joltage>], budget: usize) -> Option<Allocation> {
+    // best[j] is the best total using exactly j batteries in the banks
+    // processed so far; choices[b][j] is what bank b took to get there.
+    let mut best: Vec<Option<Joltage>> = vec![None; budget + 1];
+    best[0] = Some(Joltage::default());
+    let mut choices = Vec::with_capacity(curves.len());
+
+    for curve in curves {
+        let mut next: Vec<Option<Joltage>> = vec![None; budget + 1];
+        let mut choice = vec![0; budget + 1];
+        for (used, total) in best.iter().enumerate() {
+            let Some(total) = total else { continue };
+            for (k, value) in curve.iter().enumerate().take(budget - used + 1) {
+                let candidate = total + value;
+                let slot = &mut next[used + k];
+                if slot.as_ref().is_none_or(|current| candidate > *current) {
+                    *slot = Some(candidate);
+                    choice[used + k] = k;
+                }
+            }
+        }
+        best = next;
+        choices.push(choice);
+    }
+
+    let total = best[budget].take()?;
+    let mut counts = vec![0; curves.len()];
+    let mut remaining = budget;
+    for (bank, choice) in choices.iter().enumerate().rev() {
+        counts[bank] = choice[remaining];
+        remaining -= counts[bank];
+    }
+
+    Some(Allocation { counts, total })
+}
+
+fn prob3(prob_file: &str, budget: usize) -> Result<Allocation, Box<dyn std::error::Error>> {
+    let curves = joltage_curves(prob_file)?;
+
+    allocate_batteries(&curves, budget)
+        .ok_or_else(|| format!("Not enough batteries for a budget of {budget}").into())
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let joltage = prob1("input.txt")?;
-    println!("prob1: total joltage: {joltage}");
-    let joltage = prob2("input.txt")?;
-    println!("prob2: total joltage: {joltage}");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.first().map(String::as_str) {
+        Some("allocate") => {
+            let budget: usize = args
+                .get(1)
+                .ok_or("usage: day-3 allocate <batteries> [file]")?
+                .parse()?;
+            let input_file = args.get(2).map(String::as_str).unwrap_or("input.txt");
+
+            let allocation = prob3(input_file, budget)?;
+            println!("allocate: total joltage: {}", allocation.total);
+            for (bank, count) in allocation.counts.iter().enumerate() {
+                if *count > 0 {
+                    println!("  bank {}: {count} batteries", bank + 1);
+                }
+            }
+        }
+        Some("curve") => {
+            let mut rest = &args[1..];
+            let mut format = "csv";
+            if rest.first().map(String::as_str) == Some("--format") {
+                format = rest
+                    .get(1)
+                    .map(String::as_str)
+                    .ok_or("usage: day-3 curve [--format csv|json] [file]")?;
+                rest = &rest[2..];
+            }
+            let input_file = rest.first().map(String::as_str).unwrap_or("input.txt");
+
+            let curves = joltage_curves(input_file)?;
+            let mut out = io::BufWriter::new(io::stdout().lock());
+            match format {
+                "csv" => write_curves_csv(&mut out, &curves)?,
+                "json" => write_curves_json(&mut out, &curves)?,
+                _ => return Err(format!("Unknown curve format: {format}").into()),
+            }
+            out.flush()?;
+        }
+        _ => {
+            let input_file = args.first().map(String::as_str).unwrap_or("input.txt");
+            let joltage = prob1(input_file)?;
+            println!("prob1: total joltage: {joltage}");
+            let joltage = prob2(input_file)?;
+            println!("prob2: total joltage: {joltage}");
+        }
+    }
+
     Ok(())
 }
 
@@ -127,6 +458,32 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 mod test {
     use super::*;
 
+    fn read_banks(path: &str) -> Vec<Vec<u8>> {
+        let mut reader = JoltageReader::new(path).unwrap();
+        let mut banks = Vec::new();
+        while let Some(bank) = reader.next_bank() {
+            banks.push(bank.unwrap().to_vec());
+        }
+        banks
+    }
+
+    #[test]
+    fn check_reader() {
+        let mut reader = JoltageReader::from_reader("12\r\n\n345\n".as_bytes());
+        assert_eq!(reader.next_bank().unwrap().unwrap(), &[1, 2]);
+        assert_eq!(reader.next_bank().unwrap().unwrap(), &[3, 4, 5]);
+        assert!(reader.next_bank().is_none());
+
+        let mut reader = JoltageReader::from_reader("123\n45x6\n".as_bytes());
+        assert!(reader.next_bank().unwrap().is_ok());
+        match reader.next_bank().unwrap() {
+            Err(JoltageError::NotADigit { line, column, byte }) => {
+                assert_eq!((line, column, byte), (2, 3, b'x'));
+            }
+            other => panic!("expected a digit error, got {other:?}"),
+        }
+    }
+
     #[test]
     fn check_prob1() {
         assert_eq!(prob1("sample.txt").unwrap(), 357);
@@ -136,4 +493,99 @@ mod test {
     fn check_prob2() {
         assert_eq!(prob2("sample.txt").unwrap(), 3121910778619);
     }
+
+    #[test]
+    fn check_joltage_add() {
+        let a = Joltage::from_digits(&[9, 9, 9]);
+        let b = Joltage::from_digits(&[0, 4, 2]);
+        assert_eq!((&a + &b).to_string(), "1041");
+        assert_eq!((&Joltage::default() + &b).to_string(), "42");
+        assert!(a > b);
+    }
+
+    #[test]
+    fn check_curve() {
+        // xorshift banks with plenty of ties between digits
+        let mut state: u32 = 0x2545_f491;
+        let mut banks = read_banks("sample.txt");
+        for len in 0..40 {
+            let bank = (0..len)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    (state % 4 + 6) as u8
+                })
+                .collect();
+            banks.push(bank);
+        }
+
+        for bank in &banks {
+            let curve = joltage_curve(bank);
+            assert_eq!(curve.len(), bank.len() + 1);
+            for (k, joltage) in curve.iter().enumerate() {
+                assert_eq!(*joltage, Joltage::from_digits(&best_joltages(bank, k)));
+            }
+        }
+    }
+
+    #[test]
+    fn check_curve_output() {
+        let curves = vec![joltage_curve(&[8, 1, 9])];
+
+        let mut csv = Vec::new();
+        write_curves_csv(&mut csv, &curves).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "bank,k,joltage\n1,1,9\n1,2,89\n1,3,819\n"
+        );
+
+        let mut json = Vec::new();
+        write_curves_json(&mut json, &curves).unwrap();
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            "[\n  {\"bank\": 1, \"curve\": [\"9\", \"89\", \"819\"]}\n]\n"
+        );
+    }
+
+    #[test]
+    fn check_allocate() {
+        let allocation = prob3("sample.txt", 2).unwrap();
+        assert_eq!(allocation.total.to_string(), "98");
+        assert_eq!(allocation.counts.iter().sum::<usize>(), 2);
+
+        // every bank taking 12 cannot beat the joint allocation of 48
+        let allocation = prob3("sample.txt", 48).unwrap();
+        assert!(allocation.total.to_string().parse::<usize>().unwrap() >= 3121910778619);
+        assert!(prob3("sample.txt", 61).is_err());
+    }
+
+    #[test]
+    fn check_allocate_brute_force() {
+        let banks = read_banks("sample.txt");
+        let curves: Vec<Vec<Joltage>> = banks.iter().map(|bank| joltage_curve(bank)).collect();
+
+        for budget in 0..=8 {
+            let mut expected = Joltage::default();
+            for a in 0..=budget {
+                for b in 0..=(budget - a) {
+                    for c in 0..=(budget - a - b) {
+                        let d = budget - a - b - c;
+                        let total =
+                            &(&curves[0][a] + &curves[1][b]) + &(&curves[2][c] + &curves[3][d]);
+                        expected = expected.max(total);
+                    }
+                }
+            }
+
+            let allocation = allocate_batteries(&curves, budget).unwrap();
+            assert_eq!(allocation.total, expected, "budget {budget}");
+            let check = allocation
+                .counts
+                .iter()
+                .zip(&curves)
+                .fold(Joltage::default(), |acc, (&k, curve)| &acc + &curve[k]);
+            assert_eq!(check, expected);
+        }
+    }
 }
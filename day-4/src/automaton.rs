@@ -0,0 +1,213 @@
+use std::fmt;
+
+use crate::Grid;
+
+/// A life-like birth/survival rule over the grid's neighbourhood.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    // indexed by the number of live neighbours
+    birth: Vec<bool>,
+    survival: Vec<bool>,
+}
+
+impl Rule {
+    /// Parses a rule string such as `B3/S23`.
+    pub fn parse(spec: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let (birth, survival) = spec
+            .split_once('/')
+            .ok_or_else(|| format!("Rule must look like B3/S23: {spec}"))?;
+        let birth = birth
+            .strip_prefix('B')
+            .ok_or_else(|| format!("Rule must start with B: {spec}"))?;
+        let survival = survival
+            .strip_prefix('S')
+            .ok_or_else(|| format!("Rule must have an S part: {spec}"))?;
+
+        let counts = |digits: &str| -> Result<Vec<bool>, Box<dyn std::error::Error>> {
+            let mut counts = vec![false; 10];
+            for digit in digits.chars() {
+                let count = digit
+                    .to_digit(10)
+                    .ok_or_else(|| format!("Invalid neighbour count {digit:?} in {spec}"))?;
+                counts[count as usize] = true;
+            }
+            Ok(counts)
+        };
+
+        Ok(Self {
+            birth: counts(birth)?,
+            survival: counts(survival)?,
+        })
+    }
+
+    fn next_state(&self, alive: bool, neighbours: usize) -> bool {
+        let counts = if alive { &self.survival } else { &self.birth };
+        counts.get(neighbours).copied().unwrap_or(false)
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = |counts: &[bool]| -> String {
+            counts
+                .iter()
+                .enumerate()
+                .filter(|&(_, &on)| on)
+                .map(|(count, _)| count.to_string())
+                .collect()
+        };
+        write!(f, "B{}/S{}", digits(&self.birth), digits(&self.survival))
+    }
+}
+
+/// How a run of the automaton ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Generation `generation` was the same as the one before it.
+    FixedPoint { generation: usize },
+    /// Generation `generation` repeated the state `period` generations
+    /// earlier.  With Brent's method the cycle may have been entered
+    /// some time before it is detected.
+    Cycle { generation: usize, period: usize },
+    /// Still changing after the given number of generations.
+    Limit { generations: usize },
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::FixedPoint { generation } => {
+                write!(f, "fixed point at generation {generation}")
+            }
+            Outcome::Cycle { generation, period } => {
+                write!(
+                    f,
+                    "period {period} cycle detected at generation {generation}"
+                )
+            }
+            Outcome::Limit { generations } => {
+                write!(f, "no repeat within {generations} generations")
+            }
+        }
+    }
+}
+
+impl Grid {
+    pub fn count_rolls(&self) -> usize {
+        self.cells
+            .iter()
+            .flatten()
+            .filter(|&&cell| cell == '@')
+            .count()
+    }
+
+    /// The rule that peels rolls like `count_total_removable_rolls`:
+    /// nothing is born and a roll survives while it has at least
+    /// `threshold` neighbours.
+    pub fn removal_rule(&self) -> Rule {
+        let max_count = self.offsets.len();
+        Rule {
+            birth: vec![false; max_count + 1],
+            survival: (0..=max_count)
+                .map(|count| count >= self.threshold)
+                .collect(),
+        }
+    }
+
+    /// Advances every cell one generation at once.  Obstacles stay put
+    /// and count as live neighbours.  Returns whether anything changed.
+    pub fn step(&mut self, rule: &Rule) -> bool {
+        let mut next = self.cells.clone();
+        let mut changed = false;
+
+        for (row, cells) in next.iter_mut().enumerate() {
+            for (col, cell) in cells.iter_mut().enumerate() {
+                if *cell == '#' {
+                    continue;
+                }
+                let alive = *cell == '@';
+                let next_alive = rule.next_state(alive, self.count_adjacent_rolls(row, col));
+                if next_alive != alive {
+                    *cell = if next_alive { '@' } else { '.' };
+                    changed = true;
+                }
+            }
+        }
+
+        self.cells = next;
+        changed
+    }
+
+    /// Runs up to `max_generations` generations, stopping early at a
+    /// fixed point or cycle.  Cycles are found with Brent's method, so
+    /// only one earlier state is kept at a time.
+    pub fn run(&mut self, rule: &Rule, max_generations: usize) -> Outcome {
+        let mut saved = self.cells.clone();
+        let mut saved_generation = 0;
+        let mut power = 1;
+
+        for generation in 1..=max_generations {
+            if !self.step(rule) {
+                return Outcome::FixedPoint { generation };
+            }
+
+            if self.cells == saved {
+                return Outcome::Cycle {
+                    generation,
+                    period: generation - saved_generation,
+                };
+            }
+
+            if generation - saved_generation == power {
+                saved.clone_from(&self.cells);
+                saved_generation = generation;
+                power *= 2;
+            }
+        }
+
+        Outcome::Limit {
+            generations: max_generations,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_parse() {
+        let rule = Rule::parse("B3/S23").unwrap();
+        assert_eq!(rule.to_string(), "B3/S23");
+        assert!(rule.next_state(false, 3));
+        assert!(!rule.next_state(false, 2));
+        assert!(rule.next_state(true, 2));
+        assert!(!rule.next_state(true, 4));
+
+        assert_eq!(Rule::parse("B/S45678").unwrap().to_string(), "B/S45678");
+        assert!(Rule::parse("S23/B3").is_err());
+        assert!(Rule::parse("B3/Sx").is_err());
+    }
+
+    #[test]
+    fn test_life_patterns() {
+        let life = Rule::parse("B3/S23").unwrap();
+
+        // a block is still life
+        let mut block = Grid::from_reader("....\n.@@.\n.@@.\n....\n".as_bytes()).unwrap();
+        assert_eq!(block.run(&life, 10), Outcome::FixedPoint { generation: 1 });
+
+        // a blinker oscillates with period 2
+        let mut blinker =
+            Grid::from_reader(".....\n..@..\n..@..\n..@..\n.....\n".as_bytes()).unwrap();
+        assert!(matches!(
+            blinker.run(&life, 10),
+            Outcome::Cycle { period: 2, .. }
+        ));
+
+        // a glider never repeats on an open board within a few steps
+        let glider = ".@......\n..@.....\n@@@.....\n........\n........\n........\n";
+        let mut grid = Grid::from_reader(glider.as_bytes()).unwrap();
+        assert_eq!(grid.run(&life, 3), Outcome::Limit { generations: 3 });
+    }
+}
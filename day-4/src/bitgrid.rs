@@ -0,0 +1,284 @@
+use std::time::Instant;
+
+use crate::{Boundary, Grid, Neighbourhood};
+
+/// A grid of rolls packed 64 cells to a `u64`, for the default
+/// neighbourhood (Moore, radius 1) with empty boundaries.
+///
+/// Neighbour counts are computed for a whole word at once: the eight
+/// neighbour masks are built with shifts and fed through bitwise
+/// adders into four bit planes, one per bit of the count.  Obstacles
+/// live in a mask of their own and count as neighbours.
+pub struct BitGrid {
+    rows: usize,
+    words_per_row: usize,
+    // bit j of word w in a row is column w * 64 + j
+    words: Vec<u64>,
+    obstacles: Vec<u64>,
+    threshold: usize,
+}
+
+impl BitGrid {
+    pub fn from_grid(grid: &Grid) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut offsets = grid.offsets.clone();
+        offsets.sort();
+        if offsets != Neighbourhood::Moore(1).offsets() || grid.boundary != Boundary::Empty {
+            return Err(
+                "The bit grid only supports the Moore(1) neighbourhood with empty boundaries"
+                    .into(),
+            );
+        }
+
+        let words_per_row = grid.cols.div_ceil(64);
+        let mut words = vec![0; grid.rows * words_per_row];
+        let mut obstacles = vec![0; grid.rows * words_per_row];
+        for (row, cells) in grid.cells.iter().enumerate() {
+            for (col, &cell) in cells.iter().enumerate() {
+                let bit = 1 << (col % 64);
+                match cell {
+                    '@' => words[row * words_per_row + col / 64] |= bit,
+                    '#' => obstacles[row * words_per_row + col / 64] |= bit,
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(Self {
+            rows: grid.rows,
+            words_per_row,
+            words,
+            obstacles,
+            threshold: grid.threshold,
+        })
+    }
+
+    /// The occupied cells -- rolls or obstacles -- of a word.
+    fn word(&self, row: isize, word: usize) -> u64 {
+        if row < 0 || row >= self.rows as isize {
+            0
+        } else {
+            let index = row as usize * self.words_per_row + word;
+            self.words[index] | self.obstacles[index]
+        }
+    }
+
+    /// The three masks of a row that line up with a word: the cells to
+    /// the west, the cells themselves and the cells to the east.
+    fn row_masks(&self, row: isize, word: usize) -> [u64; 3] {
+        let centre = self.word(row, word);
+        let before = if word > 0 {
+            self.word(row, word - 1)
+        } else {
+            0
+        };
+        let after = if word + 1 < self.words_per_row {
+            self.word(row, word + 1)
+        } else {
+            0
+        };
+
+        let west = (centre << 1) | (before >> 63);
+        let east = (centre >> 1) | (after << 63);
+        [west, centre, east]
+    }
+
+    /// Mask of the rolls in a word with fewer neighbours than the
+    /// threshold.
+    fn accessible_word(&self, row: usize, word: usize) -> u64 {
+        let row = row as isize;
+        let [nw, n, ne] = self.row_masks(row - 1, word);
+        let [w, _, e] = self.row_masks(row, word);
+        let centre = self.words[row as usize * self.words_per_row + word];
+        let [sw, s, se] = self.row_masks(row + 1, word);
+
+        // count[i] holds bit i of every cell's neighbour count
+        let mut count = [0u64; 4];
+        for mask in [nw, n, ne, w, e, sw, s, se] {
+            let mut carry = mask;
+            for plane in count.iter_mut() {
+                let sum = *plane ^ carry;
+                carry &= *plane;
+                *plane = sum;
+            }
+        }
+
+        // bit sliced count < threshold, from the top bit down
+        if self.threshold >= 16 {
+            return centre;
+        }
+        let mut less = 0;
+        let mut equal = !0;
+        for (i, plane) in count.iter().enumerate().rev() {
+            if self.threshold & (1 << i) != 0 {
+                less |= equal & !plane;
+                equal &= plane;
+            } else {
+                equal &= !plane;
+            }
+        }
+
+        centre & less
+    }
+
+    fn accessible_words(&self) -> Vec<u64> {
+        (0..self.rows)
+            .flat_map(|row| (0..self.words_per_row).map(move |word| (row, word)))
+            .map(|(row, word)| self.accessible_word(row, word))
+            .collect()
+    }
+
+    pub fn count_accessible_rolls(&self) -> usize {
+        self.accessible_words()
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Removes every accessible roll together, round after round, until
+    /// none are accessible.
+    pub fn count_total_removable_rolls(&mut self) -> usize {
+        let mut total_removed = 0;
+
+        loop {
+            let accessible = self.accessible_words();
+            let count: usize = accessible
+                .iter()
+                .map(|word| word.count_ones() as usize)
+                .sum();
+            if count == 0 {
+                break;
+            }
+
+            total_removed += count;
+            for (word, removed) in self.words.iter_mut().zip(accessible) {
+                *word &= !removed;
+            }
+        }
+
+        total_removed
+    }
+
+    pub fn count_rolls(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+}
+
+/// A `rows` x `cols` grid with roughly `percent` % rolls, generated
+/// from `seed` with xorshift.
+pub fn generate_grid(rows: usize, cols: usize, percent: u64, seed: u64) -> Grid {
+    let mut state = seed.max(1);
+    let cells = (0..rows)
+        .map(|_| {
+            (0..cols)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    if state % 100 < percent { '@' } else { '.' }
+                })
+                .collect()
+        })
+        .collect();
+
+    Grid::from_cells(cells)
+}
+
+/// Times the char grid against the bit grid on a generated
+/// `size` x `size` grid.
+pub fn bench(size: usize, threshold: usize) {
+    let grid = generate_grid(size, size, 70, 0x9e37_79b9_7f4a_7c15).with_threshold(threshold);
+    println!(
+        "bench: {size}x{size} grid, {} rolls",
+        BitGrid::from_grid(&grid).unwrap().count_rolls()
+    );
+
+    let start = Instant::now();
+    let accessible = grid.count_accessible_rolls();
+    println!(
+        "  char grid accessible:  {accessible} in {:?}",
+        start.elapsed()
+    );
+
+    let start = Instant::now();
+    let bits = BitGrid::from_grid(&grid).unwrap();
+    let accessible = bits.count_accessible_rolls();
+    println!(
+        "  bit grid accessible:   {accessible} in {:?}",
+        start.elapsed()
+    );
+
+    let mut rounds = generate_grid(size, size, 70, 0x9e37_79b9_7f4a_7c15).with_threshold(threshold);
+    let start = Instant::now();
+    let removed = rounds.count_total_removable_rolls_by_rounds();
+    println!(
+        "  char grid rounds:      {removed} in {:?}",
+        start.elapsed()
+    );
+
+    let mut incremental =
+        generate_grid(size, size, 70, 0x9e37_79b9_7f4a_7c15).with_threshold(threshold);
+    let start = Instant::now();
+    let removed = incremental.count_total_removable_rolls();
+    println!(
+        "  char grid incremental: {removed} in {:?}",
+        start.elapsed()
+    );
+
+    let start = Instant::now();
+    let mut bits = BitGrid::from_grid(&grid).unwrap();
+    let removed = bits.count_total_removable_rolls();
+    println!(
+        "  bit grid rounds:       {removed} in {:?}",
+        start.elapsed()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_char_grid() {
+        let mut grids = vec![
+            Grid::from_file("sample.txt").unwrap(),
+            Grid::from_file("input.txt").unwrap(),
+        ];
+        for (seed, cols) in [(1, 1), (2, 63), (3, 64), (4, 65), (5, 130)] {
+            grids.push(generate_grid(40, cols, 65, seed));
+        }
+        let mut obstacles = generate_grid(30, 100, 70, 6);
+        for (row, cells) in obstacles.cells.iter_mut().enumerate() {
+            for cell in cells.iter_mut().skip(row % 7).step_by(7) {
+                *cell = '#';
+            }
+        }
+        grids.push(obstacles);
+
+        for grid in grids {
+            for threshold in [0, 1, 4, 5, 8, 9, 20] {
+                let mut grid = Grid::from_cells(grid.cells.clone()).with_threshold(threshold);
+                let mut bits = BitGrid::from_grid(&grid).unwrap();
+                assert_eq!(bits.count_accessible_rolls(), grid.count_accessible_rolls());
+                assert_eq!(
+                    bits.count_total_removable_rolls(),
+                    grid.count_total_removable_rolls()
+                );
+                assert_eq!(
+                    bits.count_rolls(),
+                    BitGrid::from_grid(&grid).unwrap().count_rolls()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_unsupported_neighbourhood() {
+        let grid = Grid::from_file("sample.txt")
+            .unwrap()
+            .with_neighbourhood(&Neighbourhood::VonNeumann(1));
+        assert!(BitGrid::from_grid(&grid).is_err());
+    }
+}
@@ -0,0 +1,200 @@
+use std::io::{self, Write};
+
+use crate::Grid;
+
+/// Which neighbouring rolls belong to the same component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Rolls sharing an edge.
+    Four,
+    /// Rolls sharing an edge or a corner.
+    Eight,
+}
+
+impl Connectivity {
+    pub fn parse(spec: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        match spec {
+            "4" => Ok(Connectivity::Four),
+            "8" => Ok(Connectivity::Eight),
+            _ => Err(format!("Connectivity must be 4 or 8: {spec}").into()),
+        }
+    }
+
+    fn offsets(&self) -> &'static [(isize, isize)] {
+        match self {
+            Connectivity::Four => &[(-1, 0), (0, -1), (0, 1), (1, 0)],
+            Connectivity::Eight => &[
+                (-1, -1),
+                (-1, 0),
+                (-1, 1),
+                (0, -1),
+                (0, 1),
+                (1, -1),
+                (1, 0),
+                (1, 1),
+            ],
+        }
+    }
+}
+
+/// A connected group of rolls and its bounding box, inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Component {
+    pub size: usize,
+    pub top: usize,
+    pub left: usize,
+    pub bottom: usize,
+    pub right: usize,
+}
+
+/// The component label of every roll, and the components themselves
+/// in row-major order of their first roll.
+#[derive(Debug)]
+pub struct Labelling {
+    pub labels: Vec<Vec<Option<usize>>>,
+    pub components: Vec<Component>,
+}
+
+impl Labelling {
+    /// Writes one `row,col,component` line per roll.
+    pub fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "row,col,component")?;
+        for (row, labels) in self.labels.iter().enumerate() {
+            for (col, label) in labels.iter().enumerate() {
+                if let Some(label) = label {
+                    writeln!(out, "{row},{col},{label}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Grid {
+    /// The rolls that are never removed, as a grid of their own with
+    /// the same settings.
+    pub fn stable_core(&self) -> Grid {
+        let mut core = self.clone();
+        core.count_total_removable_rolls();
+        core
+    }
+
+    /// Labels the connected components of the rolls.  Edges that wrap
+    /// under the boundary policy connect as well.
+    pub fn components(&self, connectivity: Connectivity) -> Labelling {
+        let mut labels = vec![vec![None; self.cols]; self.rows];
+        let mut components = Vec::new();
+        let mut stack = Vec::new();
+
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                if self.cells[row][col] != '@' || labels[row][col].is_some() {
+                    continue;
+                }
+
+                let label = components.len();
+                let mut component = Component {
+                    size: 0,
+                    top: row,
+                    left: col,
+                    bottom: row,
+                    right: col,
+                };
+                labels[row][col] = Some(label);
+                stack.push((row, col));
+
+                while let Some((r, c)) = stack.pop() {
+                    component.size += 1;
+                    component.top = component.top.min(r);
+                    component.left = component.left.min(c);
+                    component.bottom = component.bottom.max(r);
+                    component.right = component.right.max(c);
+
+                    for &(dr, dc) in connectivity.offsets() {
+                        if let Some((nr, nc)) = self.neighbour(r, c, dr, dc)
+                            && self.cells[nr][nc] == '@'
+                            && labels[nr][nc].is_none()
+                        {
+                            labels[nr][nc] = Some(label);
+                            stack.push((nr, nc));
+                        }
+                    }
+                }
+
+                components.push(component);
+            }
+        }
+
+        Labelling { labels, components }
+    }
+
+    /// Writes the grid in the alphabet it was read in.
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        for cells in &self.cells {
+            let line: String = cells
+                .iter()
+                .map(|&cell| self.alphabet.encode(cell))
+                .collect();
+            writeln!(out, "{line}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_components() {
+        let grid = Grid::from_reader("@@..@\n@..@@\n..@..\n".as_bytes()).unwrap();
+
+        let four = grid.components(Connectivity::Four);
+        assert_eq!(four.components.len(), 3);
+        assert_eq!(
+            four.components[0],
+            Component {
+                size: 3,
+                top: 0,
+                left: 0,
+                bottom: 1,
+                right: 1
+            }
+        );
+        assert_eq!(four.labels[1][4], Some(1));
+        assert_eq!(four.labels[2][2], Some(2));
+        assert_eq!(four.labels[0][2], None);
+
+        let mut csv = Vec::new();
+        four.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with("row,col,component\n0,0,0\n"));
+        assert!(csv.ends_with("1,4,1\n2,2,2\n"));
+
+        // the middle roll touches the right column diagonally
+        let eight = grid.components(Connectivity::Eight);
+        assert_eq!(eight.components.len(), 2);
+        assert_eq!(eight.components[1].size, 4);
+        assert_eq!(
+            (eight.components[1].left, eight.components[1].right),
+            (2, 4)
+        );
+    }
+
+    #[test]
+    fn test_stable_core() {
+        let grid = Grid::from_file("sample.txt").unwrap();
+        let core = grid.stable_core();
+        assert_eq!(grid.count_rolls() - core.count_rolls(), 43);
+        assert_eq!(core.count_accessible_rolls(), 0);
+
+        let mut out = Vec::new();
+        core.write(&mut out).unwrap();
+        let reread = Grid::from_reader(out.as_slice()).unwrap();
+        assert_eq!(reread.cells, core.cells);
+
+        let labelling = core.components(Connectivity::Eight);
+        let total: usize = labelling.components.iter().map(|c| c.size).sum();
+        assert_eq!(total, core.count_rolls());
+    }
+}
@@ -0,0 +1,222 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::{Alphabet, Grid};
+
+use crate::rounds::{Removal, RoundMap};
+
+/// The file format of animation frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameFormat {
+    /// Binary colour PPM, with every cell drawn as a `scale` x `scale`
+    /// block of pixels.
+    Ppm { scale: usize },
+    /// Text in the grid's own symbols, with `x` marking the rolls
+    /// removed in the frame's round, or the first of `*+o%` that the
+    /// alphabet leaves free.
+    Ascii,
+}
+
+impl Default for FrameFormat {
+    fn default() -> Self {
+        FrameFormat::Ppm { scale: 4 }
+    }
+}
+
+impl FrameFormat {
+    /// Parses `ppm`, `ppm:<scale>` or `ascii`.
+    pub fn parse(spec: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        match spec.split_once(':') {
+            None if spec == "ppm" => Ok(FrameFormat::default()),
+            None if spec == "ascii" => Ok(FrameFormat::Ascii),
+            Some(("ppm", scale)) => match scale.parse()? {
+                0 => Err("Frame scale must be at least 1".into()),
+                scale => Ok(FrameFormat::Ppm { scale }),
+            },
+            _ => Err(format!("Unknown frame format: {spec}").into()),
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            FrameFormat::Ppm { .. } => "ppm",
+            FrameFormat::Ascii => "txt",
+        }
+    }
+}
+
+/// What a cell shows in a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameCell {
+    Empty,
+    Roll,
+    Obstacle,
+    // removed in the frame's round
+    Removed,
+}
+
+impl Alphabet {
+    /// The symbol for rolls removed in a frame's round, one that can't
+    /// be mistaken for any other cell.
+    fn removed_marker(&self) -> char {
+        ['x', '*', '+', 'o', '%']
+            .into_iter()
+            .find(|&marker| self.decode(marker).is_none())
+            .expect("three symbols can't take all five markers")
+    }
+}
+
+impl RoundMap {
+    /// The cell as it looks in frame `frame`: frame 0 is the initial
+    /// grid and frame r shows the rolls removed in round r highlighted.
+    fn frame_cell(removal: Removal, frame: usize) -> FrameCell {
+        match removal {
+            Removal::NoRoll => FrameCell::Empty,
+            Removal::Obstacle => FrameCell::Obstacle,
+            Removal::Round(round) if round < frame => FrameCell::Empty,
+            Removal::Round(round) if round == frame => FrameCell::Removed,
+            Removal::Round(_) | Removal::Never => FrameCell::Roll,
+        }
+    }
+
+    fn write_ascii_frame(
+        &self,
+        out: &mut impl Write,
+        frame: usize,
+        alphabet: &Alphabet,
+    ) -> io::Result<()> {
+        let marker = alphabet.removed_marker();
+        for cells in &self.cells {
+            let line: String = cells
+                .iter()
+                .map(|&removal| match Self::frame_cell(removal, frame) {
+                    FrameCell::Empty => alphabet.encode('.'),
+                    FrameCell::Roll => alphabet.encode('@'),
+                    FrameCell::Obstacle => alphabet.encode('#'),
+                    FrameCell::Removed => marker,
+                })
+                .collect();
+            writeln!(out, "{line}")?;
+        }
+        Ok(())
+    }
+
+    fn write_ppm_frame(&self, out: &mut impl Write, frame: usize, scale: usize) -> io::Result<()> {
+        let (rows, cols) = (self.cells.len(), self.cells.first().map_or(0, Vec::len));
+        write!(out, "P6\n{} {}\n255\n", cols * scale, rows * scale)?;
+
+        let mut line = Vec::with_capacity(cols * scale * 3);
+        for cells in &self.cells {
+            line.clear();
+            for &removal in cells {
+                let pixel = match Self::frame_cell(removal, frame) {
+                    FrameCell::Empty => [0, 0, 0],
+                    FrameCell::Roll => [200, 200, 200],
+                    FrameCell::Obstacle => [64, 64, 160],
+                    FrameCell::Removed => [255, 32, 32],
+                };
+                for _ in 0..scale {
+                    line.extend_from_slice(&pixel);
+                }
+            }
+            for _ in 0..scale {
+                out.write_all(&line)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes frames 0 to `rounds` into `dir` as `frame-0000.ppm` (or
+    /// `.txt`) and so on, creating the directory if needed.  Text
+    /// frames are written in `alphabet`.
+    pub fn write_frames(
+        &self,
+        dir: &Path,
+        format: FrameFormat,
+        alphabet: &Alphabet,
+    ) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+
+        for frame in 0..=self.rounds {
+            let path = dir.join(format!("frame-{frame:04}.{}", format.extension()));
+            let mut out = io::BufWriter::new(File::create(path)?);
+            match format {
+                FrameFormat::Ppm { scale } => self.write_ppm_frame(&mut out, frame, scale)?,
+                FrameFormat::Ascii => self.write_ascii_frame(&mut out, frame, alphabet)?,
+            }
+            out.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl Grid {
+    /// `count_total_removable_rolls`, also writing one frame per round
+    /// into `dir` with the rolls removed in that round highlighted.
+    pub fn count_total_removable_rolls_animated(
+        &mut self,
+        dir: &Path,
+        format: FrameFormat,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let map = self.removal_rounds();
+        map.write_frames(dir, format, &self.alphabet)?;
+        Ok(map.removed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames() {
+        let dir = std::env::temp_dir().join(format!("day-4-frames-{}", std::process::id()));
+        let mut grid = Grid::from_reader("#@@\n.@.\n@@@\n".as_bytes()).unwrap();
+        let removed = grid
+            .count_total_removable_rolls_animated(&dir, FrameFormat::Ascii)
+            .unwrap();
+        assert_eq!(removed, 6);
+
+        let frame = |n: usize| fs::read_to_string(dir.join(format!("frame-{n:04}.txt"))).unwrap();
+        assert_eq!(frame(0), "#@@\n.@.\n@@@\n");
+        assert_eq!(frame(1), "#xx\n.@.\nxxx\n");
+        assert_eq!(frame(2), "#..\n.x.\n...\n");
+        assert!(!dir.join("frame-0003.txt").exists());
+
+        // Frames keep a custom alphabet, and the marker steps aside
+        // when the alphabet uses `x`
+        let alphabet = Alphabet::parse("ox*").unwrap();
+        let mut grid =
+            Grid::from_reader_with_alphabet("*oo\nxox\nooo\n".as_bytes(), alphabet).unwrap();
+        grid.count_total_removable_rolls_animated(&dir, FrameFormat::Ascii)
+            .unwrap();
+        assert_eq!(frame(0), "*oo\nxox\nooo\n");
+        assert_eq!(frame(1), "*++\nxox\n+++\n");
+        assert_eq!(frame(2), "*xx\nx+x\nxxx\n");
+
+        let mut grid = Grid::from_reader("@@\n".as_bytes()).unwrap();
+        grid.count_total_removable_rolls_animated(&dir, FrameFormat::Ppm { scale: 3 })
+            .unwrap();
+        let ppm = fs::read(dir.join("frame-0001.ppm")).unwrap();
+        assert!(ppm.starts_with(b"P6\n6 3\n255\n"));
+        assert_eq!(ppm.len(), "P6\n6 3\n255\n".len() + 6 * 3 * 3);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_frame_format_parse() {
+        assert_eq!(
+            FrameFormat::parse("ppm").unwrap(),
+            FrameFormat::Ppm { scale: 4 }
+        );
+        assert_eq!(
+            FrameFormat::parse("ppm:1").unwrap(),
+            FrameFormat::Ppm { scale: 1 }
+        );
+        assert_eq!(FrameFormat::parse("ascii").unwrap(), FrameFormat::Ascii);
+        assert!(FrameFormat::parse("ppm:0").is_err());
+        assert!(FrameFormat::parse("gif").is_err());
+    }
+}
@@ -1,85 +1,439 @@
 use std::fs::File;
-use std::io::{self, BufRead};
+use std::io::{self, BufRead, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::thread;
 
+mod automaton;
+mod bitgrid;
+mod components;
+mod frames;
+mod rounds;
+mod semantics;
+mod voxel;
+
+use automaton::Rule;
+use bitgrid::BitGrid;
+use components::Connectivity;
+use frames::FrameFormat;
+use rounds::{Removal, RoundMap};
+use semantics::Semantics;
+use voxel::VoxelGrid;
+
+/// The cells around a roll that count towards its adjacency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Neighbourhood {
+    /// Cells within the given Manhattan distance.
+    VonNeumann(usize),
+    /// Cells within the given Chebyshev distance.
+    Moore(usize),
+    /// Explicit (row, col) offsets, e.g. read from a kernel file.
+    Custom(Vec<(isize, isize)>),
+}
+
+impl Default for Neighbourhood {
+    fn default() -> Self {
+        Neighbourhood::Moore(1)
+    }
+}
+
+impl Neighbourhood {
+    /// Reads a kernel file: an odd-sized block of `#` (neighbour) and
+    /// `.` (ignored) cells centred on the roll being examined.
+    fn from_kernel_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let file = File::open(path)?;
+        let rows: Vec<Vec<char>> = io::BufReader::new(file)
+            .lines()
+            .map(|line| line.map(|line| line.trim_end().chars().collect()))
+            .collect::<Result<_, _>>()?;
+
+        let height = rows.len();
+        let width = rows.first().map_or(0, |row| row.len());
+        if height.is_multiple_of(2) || width.is_multiple_of(2) {
+            return Err(format!("Kernel must have odd dimensions, got {height}x{width}").into());
+        }
+
+        let (center_row, center_col) = ((height / 2) as isize, (width / 2) as isize);
+        let mut offsets = Vec::new();
+        for (r, row) in rows.iter().enumerate() {
+            if row.len() != width {
+                return Err(format!(
+                    "Kernel line {} has {} cells, expected {width}",
+                    r + 1,
+                    row.len()
+                )
+                .into());
+            }
+            for (c, &cell) in row.iter().enumerate() {
+                let offset = (r as isize - center_row, c as isize - center_col);
+                match cell {
+                    '#' if offset == (0, 0) => {
+                        return Err("Kernel must not include its centre cell".into());
+                    }
+                    '#' => offsets.push(offset),
+                    '.' => {}
+                    _ => {
+                        return Err(
+                            format!("Invalid kernel cell {cell:?} on line {}", r + 1).into()
+                        );
+                    }
+                }
+            }
+        }
+
+        Ok(Neighbourhood::Custom(offsets))
+    }
+
+    /// Parses a command line neighbourhood: `moore[:r]`,
+    /// `von-neumann[:r]` or `kernel:<file>`.
+    fn parse(spec: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let (kind, arg) = match spec.split_once(':') {
+            Some((kind, arg)) => (kind, Some(arg)),
+            None => (spec, None),
+        };
+        let radius = || -> Result<usize, Box<dyn std::error::Error>> {
+            Ok(arg.map(str::parse).transpose()?.unwrap_or(1))
+        };
+
+        match kind {
+            "moore" => Ok(Neighbourhood::Moore(radius()?)),
+            "von-neumann" => Ok(Neighbourhood::VonNeumann(radius()?)),
+            "kernel" => Self::from_kernel_file(arg.ok_or("kernel neighbourhood needs a file")?),
+            _ => Err(format!("Unknown neighbourhood: {spec}").into()),
+        }
+    }
+
+    fn offsets(&self) -> Vec<(isize, isize)> {
+        match self {
+            Neighbourhood::VonNeumann(radius) | Neighbourhood::Moore(radius) => {
+                let r = *radius as isize;
+                let mut offsets = Vec::new();
+                for dr in -r..=r {
+                    for dc in -r..=r {
+                        let within = match self {
+                            Neighbourhood::VonNeumann(_) => dr.abs() + dc.abs() <= r,
+                            _ => true,
+                        };
+                        if within && (dr, dc) != (0, 0) {
+                            offsets.push((dr, dc));
+                        }
+                    }
+                }
+                offsets
+            }
+            Neighbourhood::Custom(offsets) => offsets.clone(),
+        }
+    }
+}
+
+/// How neighbour queries treat cells beyond the edge of the grid.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Boundary {
+    /// Off-grid cells are empty floor.
+    #[default]
+    Empty,
+    /// Off-grid cells are walls and count as occupied.
+    Wall,
+    /// The left and right edges join up, as on a cylindrical rack.
+    WrapHorizontal,
+    /// Both pairs of edges join up.
+    WrapBoth,
+}
+
+impl Boundary {
+    fn parse(spec: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        match spec {
+            "empty" => Ok(Boundary::Empty),
+            "wall" => Ok(Boundary::Wall),
+            "wrap-horizontal" => Ok(Boundary::WrapHorizontal),
+            "wrap-both" => Ok(Boundary::WrapBoth),
+            _ => Err(format!("Unknown boundary: {spec}").into()),
+        }
+    }
+}
+
+/// The symbols a floor plan is written in.  Internally cells always
+/// hold `@` for a roll, `.` for empty floor and `#` for a fixed
+/// obstacle, which is never removed but counts as a neighbour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Alphabet {
+    roll: char,
+    empty: char,
+    obstacle: char,
+}
+
+impl Default for Alphabet {
+    fn default() -> Self {
+        Self {
+            roll: '@',
+            empty: '.',
+            obstacle: '#',
+        }
+    }
+}
+
+impl Alphabet {
+    /// Parses three distinct symbols: roll, empty floor, obstacle.
+    fn parse(spec: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let symbols: Vec<char> = spec.chars().collect();
+        let [roll, empty, obstacle] = symbols[..] else {
+            return Err(
+                format!("Alphabet must be roll, empty and obstacle symbols: {spec}").into(),
+            );
+        };
+        if roll == empty || roll == obstacle || empty == obstacle {
+            return Err(format!("Alphabet symbols must be distinct: {spec}").into());
+        }
+
+        Ok(Self {
+            roll,
+            empty,
+            obstacle,
+        })
+    }
+
+    fn decode(&self, symbol: char) -> Option<char> {
+        match symbol {
+            _ if symbol == self.roll => Some('@'),
+            _ if symbol == self.empty => Some('.'),
+            _ if symbol == self.obstacle => Some('#'),
+            _ => None,
+        }
+    }
+
+    fn encode(&self, cell: char) -> char {
+        match cell {
+            '@' => self.roll,
+            '#' => self.obstacle,
+            _ => self.empty,
+        }
+    }
+}
+
+#[derive(Clone)]
 struct Grid {
     cells: Vec<Vec<char>>,
     rows: usize,
     cols: usize,
+    offsets: Vec<(isize, isize)>,
+    // a roll is accessible when fewer than this many neighbours are
+    // rolls or obstacles
+    threshold: usize,
+    boundary: Boundary,
+    alphabet: Alphabet,
+    // worker threads for whole-grid scans, 1 for serial
+    threads: usize,
 }
 
 impl Grid {
+    const DEFAULT_THRESHOLD: usize = 4;
+
     fn from_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
         let file = File::open(path)?;
-        let lines = io::BufReader::new(file).lines();
+        Self::from_reader(io::BufReader::new(file))
+    }
 
-        let cells: Vec<Vec<char>> = lines
-            .map(|line| line.expect("Failed to read line").chars().collect())
-            .collect();
+    fn from_file_with_alphabet(
+        path: &str,
+        alphabet: Alphabet,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let file = File::open(path)?;
+        Self::from_reader_with_alphabet(io::BufReader::new(file), alphabet)
+    }
+
+    fn from_reader(reader: impl BufRead) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_reader_with_alphabet(reader, Alphabet::default())
+    }
+
+    /// Reads a rectangular floor plan, rejecting ragged rows and
+    /// symbols outside the alphabet.  Blank lines at the end are
+    /// ignored.
+    fn from_reader_with_alphabet(
+        reader: impl BufRead,
+        alphabet: Alphabet,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut lines = reader.lines().collect::<Result<Vec<String>, _>>()?;
+        while lines
+            .last()
+            .is_some_and(|line| line.trim_end_matches('\r').is_empty())
+        {
+            lines.pop();
+        }
+
+        let mut cells = Vec::with_capacity(lines.len());
+        for (i, line) in lines.iter().enumerate() {
+            let row = line
+                .trim_end_matches('\r')
+                .chars()
+                .enumerate()
+                .map(|(col, symbol)| {
+                    alphabet.decode(symbol).ok_or_else(|| {
+                        format!(
+                            "line {}, column {}: unknown symbol {symbol:?}",
+                            i + 1,
+                            col + 1
+                        )
+                    })
+                })
+                .collect::<Result<Vec<char>, _>>()?;
 
+            if let Some(first) = cells.first().map(Vec::len)
+                && row.len() != first
+            {
+                return Err(format!(
+                    "line {}: expected {first} cells like line 1, found {}",
+                    i + 1,
+                    row.len()
+                )
+                .into());
+            }
+            cells.push(row);
+        }
+
+        let mut grid = Self::from_cells(cells);
+        grid.alphabet = alphabet;
+        Ok(grid)
+    }
+
+    fn from_cells(cells: Vec<Vec<char>>) -> Self {
         let rows = cells.len();
         let cols = if rows > 0 { cells[0].len() } else { 0 };
 
-        Ok(Self { cells, rows, cols })
+        Self {
+            cells,
+            rows,
+            cols,
+            offsets: Neighbourhood::default().offsets(),
+            threshold: Self::DEFAULT_THRESHOLD,
+            boundary: Boundary::default(),
+            alphabet: Alphabet::default(),
+            threads: 1,
+        }
+    }
+
+    fn with_neighbourhood(mut self, neighbourhood: &Neighbourhood) -> Self {
+        self.offsets = neighbourhood.offsets();
+        self
     }
 
-    fn count_adjacent_rolls(&self, row: usize, col: usize) -> usize {
-        let directions = [
-            (-1, -1), (-1, 0), (-1, 1),
-            (0, -1),           (0, 1),
-            (1, -1),  (1, 0),  (1, 1),
-        ];
+    fn with_threshold(mut self, threshold: usize) -> Self {
+        self.threshold = threshold;
+        self
+    }
 
-        let mut count = 0;
-        for (dr, dc) in directions {
-            let new_row = row as isize + dr;
-            let new_col = col as isize + dc;
+    fn with_boundary(mut self, boundary: Boundary) -> Self {
+        self.boundary = boundary;
+        self
+    }
 
-            if new_row >= 0 && new_row < self.rows as isize
-                && new_col >= 0 && new_col < self.cols as isize
-            {
-                let r = new_row as usize;
-                let c = new_col as usize;
-                if self.cells[r][c] == '@' {
-                    count += 1;
-                }
+    fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Maps a possibly off-grid index back onto 0..len, wrapping
+    /// around if asked to.
+    fn resolve(index: isize, len: usize, wrap: bool) -> Option<usize> {
+        if wrap && len > 0 {
+            Some(index.rem_euclid(len as isize) as usize)
+        } else if index >= 0 && index < len as isize {
+            Some(index as usize)
+        } else {
+            None
+        }
+    }
+
+    /// The cell at offset (dr, dc) from (row, col), wrapping around the
+    /// edges as the boundary policy dictates.  `None` for cells beyond
+    /// a non-wrapping edge.
+    fn neighbour(&self, row: usize, col: usize, dr: isize, dc: isize) -> Option<(usize, usize)> {
+        let wrap_rows = self.boundary == Boundary::WrapBoth;
+        let wrap_cols = matches!(self.boundary, Boundary::WrapHorizontal | Boundary::WrapBoth);
+
+        let new_row = Self::resolve(row as isize + dr, self.rows, wrap_rows)?;
+        let new_col = Self::resolve(col as isize + dc, self.cols, wrap_cols)?;
+        Some((new_row, new_col))
+    }
+
+    /// Counts the occupied neighbours of a cell: rolls, obstacles and,
+    /// with `Boundary::Wall`, off-grid cells.
+    fn count_adjacent_rolls(&self, row: usize, col: usize) -> usize {
+        let mut count = 0;
+        for &(dr, dc) in &self.offsets {
+            match self.neighbour(row, col, dr, dc) {
+                Some((r, c)) if self.cells[r][c] != '.' => count += 1,
+                None if self.boundary == Boundary::Wall => count += 1,
+                _ => {}
             }
         }
 
         count
     }
 
+    fn is_accessible(&self, row: usize, col: usize) -> bool {
+        self.cells[row][col] == '@' && self.count_adjacent_rolls(row, col) < self.threshold
+    }
+
+    /// Runs `scan` over bands of consecutive rows, one band per worker
+    /// thread, and returns the results in row order.
+    ///
+    /// Workers only read the grid, so the neighbour queries at the edge
+    /// of a band look straight into the halo rows of the bands above
+    /// and below (or across a wrapped edge); nothing is removed until
+    /// every band is done.
+    fn scan_bands<T: Send>(&self, scan: impl Fn(Range<usize>) -> T + Sync) -> Vec<T> {
+        let threads = self.threads.min(self.rows);
+        if threads <= 1 {
+            return vec![scan(0..self.rows)];
+        }
+
+        let band = self.rows.div_ceil(threads);
+        let scan = &scan;
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..self.rows)
+                .step_by(band)
+                .map(|start| scope.spawn(move || scan(start..(start + band).min(self.rows))))
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| worker.join().expect("Scan worker panicked"))
+                .collect()
+        })
+    }
+
     fn count_accessible_rolls(&self) -> usize {
-        let mut accessible = 0;
+        self.scan_bands(|rows| {
+            let mut accessible = 0;
 
-        for row in 0..self.rows {
-            for col in 0..self.cols {
-                if self.cells[row][col] == '@' {
-                    let adjacent_count = self.count_adjacent_rolls(row, col);
-                    if adjacent_count < 4 {
+            for row in rows {
+                for col in 0..self.cols {
+                    if self.is_accessible(row, col) {
                         accessible += 1;
                     }
                 }
             }
-        }
 
-        accessible
+            accessible
+        })
+        .into_iter()
+        .sum()
     }
 
     fn find_accessible_positions(&self) -> Vec<(usize, usize)> {
-        let mut positions = Vec::new();
+        self.scan_bands(|rows| {
+            let mut positions = Vec::new();
 
-        for row in 0..self.rows {
-            for col in 0..self.cols {
-                if self.cells[row][col] == '@' {
-                    let adjacent_count = self.count_adjacent_rolls(row, col);
-                    if adjacent_count < 4 {
+            for row in rows {
+                for col in 0..self.cols {
+                    if self.is_accessible(row, col) {
                         positions.push((row, col));
                     }
                 }
             }
-        }
 
-        positions
+            positions
+        })
+        .concat()
     }
 
     fn remove_rolls(&mut self, positions: &[(usize, usize)]) {
@@ -88,7 +442,105 @@ impl Grid {
         }
     }
 
+    /// Removes rolls until none are accessible and returns how many
+    /// were removed.
     fn count_total_removable_rolls(&mut self) -> usize {
+        let mut removed = 0;
+        self.peel(|_, _, _| removed += 1);
+        removed
+    }
+
+    /// Removes rolls until none are accessible, recording the round in
+    /// which each roll goes.  Rolls accessible at the start go in round
+    /// 1, those accessible once round 1 is gone in round 2, and so on.
+    fn removal_rounds(&mut self) -> RoundMap {
+        let mut map = RoundMap::new(self);
+        map.rounds = self.peel(|row, col, round| map.cells[row][col] = Removal::Round(round));
+        map
+    }
+
+    /// Removes rolls round by round until none are accessible, calling
+    /// `removed` with the position and round of each, and returns the
+    /// number of rounds.
+    ///
+    /// Instead of rescanning the grid every round, the adjacent roll
+    /// count of every roll is kept up to date as rolls are removed, and
+    /// only rolls whose count dropped below the threshold are queued
+    /// for the next round.  Each roll is queued at most once, so the
+    /// work is proportional to the number of cells times the
+    /// neighbourhood size, and the extra memory is five bytes a cell.
+    /// Removing a roll only ever lowers the counts of others, so the
+    /// rounds match `count_total_removable_rolls_by_rounds`.
+    fn peel(&mut self, mut removed: impl FnMut(usize, usize, usize)) -> usize {
+        // Seed the counts and the first round band by band; the bands
+        // come back in row order, so concatenating them keeps both
+        // row-major
+        let bands = self.scan_bands(|rows| {
+            let mut counts = Vec::with_capacity(rows.len() * self.cols);
+            let mut accessible = Vec::new();
+            for row in rows {
+                for col in 0..self.cols {
+                    let count = if self.cells[row][col] == '@' {
+                        self.count_adjacent_rolls(row, col)
+                    } else {
+                        0
+                    };
+                    if self.cells[row][col] == '@' && count < self.threshold {
+                        accessible.push(row * self.cols + col);
+                    }
+                    counts.push(count as u32);
+                }
+            }
+            (counts, accessible)
+        });
+
+        let mut counts = Vec::with_capacity(self.rows * self.cols);
+        let mut queued = vec![false; self.rows * self.cols];
+        // queued rolls as row-major indices
+        let mut current = Vec::new();
+        for (band_counts, accessible) in bands {
+            counts.extend(band_counts);
+            for &index in &accessible {
+                queued[index] = true;
+            }
+            current.extend(accessible);
+        }
+
+        let mut round = 0;
+        while !current.is_empty() {
+            round += 1;
+            let mut next = Vec::new();
+            for index in current {
+                let (row, col) = (index / self.cols, index % self.cols);
+                self.cells[row][col] = '.';
+                removed(row, col, round);
+
+                // the rolls that count this one are found at the opposite
+                // offsets -- custom kernels need not be symmetric
+                for &(dr, dc) in &self.offsets {
+                    let Some((r, c)) = self.neighbour(row, col, -dr, -dc) else {
+                        continue;
+                    };
+                    let index = r * self.cols + c;
+                    if self.cells[r][c] != '@' || queued[index] {
+                        continue;
+                    }
+                    counts[index] -= 1;
+                    if (counts[index] as usize) < self.threshold {
+                        queued[index] = true;
+                        next.push(index);
+                    }
+                }
+            }
+            current = next;
+        }
+
+        round
+    }
+
+    /// Removes all accessible rolls together, round after round, until
+    /// none are accessible.  Rescans the whole grid every round.
+    fn count_total_removable_rolls_by_rounds(&mut self) -> usize {
         let mut total_removed = 0;
 
         loop {
@@ -106,21 +558,241 @@ impl Grid {
     }
 }
 
+struct Options {
+    input_file: String,
+    neighbourhood: Neighbourhood,
+    threshold: usize,
+    // rescan the whole grid every round instead of tracking counts
+    rounds: bool,
+    heatmap: Option<String>,
+    frames: Option<String>,
+    frame_format: FrameFormat,
+    rounds_csv: Option<String>,
+    boundary: Boundary,
+    bitgrid: bool,
+    bench: Option<usize>,
+    semantics: Vec<Semantics>,
+    // a rule string, or `removal` for the rule matching part 2
+    rule: Option<String>,
+    generations: usize,
+    core: Option<String>,
+    components: Option<Connectivity>,
+    labels_csv: Option<String>,
+    alphabet: Option<Alphabet>,
+    // the input is stacked 2D layers
+    voxels: bool,
+    threads: usize,
+}
+
+impl Options {
+    const USAGE: &str = "usage: day-4 [--alphabet <roll><empty><obstacle>] \
+                         [--neighbourhood moore[:r]|von-neumann[:r]|kernel:<file>] \
+                         [--threshold n] [--boundary empty|wall|wrap-horizontal|wrap-both] \
+                         [--rounds] [--threads n] [--bitgrid] [--bench size] [--3d] \
+                         [--semantics simultaneous|sequential|priority:<file>[,...]] \
+                         [--rule B3/S23|removal [--generations n]] [--heatmap out.pgm|out.ppm] \
+                         [--frames dir [--frame-format ppm[:scale]|ascii]] \
+                         [--rounds-csv out.csv] [--core out.txt] \
+                         [--components 4|8 [--labels-csv out.csv]] [file]";
+
+    fn from_args() -> Result<Self, Box<dyn std::error::Error>> {
+        let mut options = Options {
+            input_file: "input.txt".to_string(),
+            neighbourhood: Neighbourhood::default(),
+            threshold: Grid::DEFAULT_THRESHOLD,
+            rounds: false,
+            heatmap: None,
+            frames: None,
+            frame_format: FrameFormat::default(),
+            rounds_csv: None,
+            boundary: Boundary::default(),
+            bitgrid: false,
+            bench: None,
+            semantics: Vec::new(),
+            rule: None,
+            generations: 1000,
+            core: None,
+            components: None,
+            labels_csv: None,
+            alphabet: None,
+            voxels: false,
+            threads: 1,
+        };
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--alphabet" => {
+                    options.alphabet = Some(Alphabet::parse(&args.next().ok_or(Self::USAGE)?)?);
+                }
+                "--neighbourhood" => {
+                    options.neighbourhood = Neighbourhood::parse(&args.next().ok_or(Self::USAGE)?)?;
+                }
+                "--threshold" => {
+                    options.threshold = args.next().ok_or(Self::USAGE)?.parse()?;
+                }
+                "--boundary" => {
+                    options.boundary = Boundary::parse(&args.next().ok_or(Self::USAGE)?)?;
+                }
+                "--rounds" => options.rounds = true,
+                "--bitgrid" => options.bitgrid = true,
+                "--3d" => options.voxels = true,
+                "--threads" => options.threads = args.next().ok_or(Self::USAGE)?.parse()?,
+                "--rule" => options.rule = Some(args.next().ok_or(Self::USAGE)?),
+                "--generations" => {
+                    options.generations = args.next().ok_or(Self::USAGE)?.parse()?;
+                }
+                "--semantics" => {
+                    for spec in args.next().ok_or(Self::USAGE)?.split(',') {
+                        options.semantics.push(Semantics::parse(spec)?);
+                    }
+                }
+                "--bench" => options.bench = Some(args.next().ok_or(Self::USAGE)?.parse()?),
+                "--core" => options.core = Some(args.next().ok_or(Self::USAGE)?),
+                "--components" => {
+                    options.components =
+                        Some(Connectivity::parse(&args.next().ok_or(Self::USAGE)?)?);
+                }
+                "--labels-csv" => options.labels_csv = Some(args.next().ok_or(Self::USAGE)?),
+                "--heatmap" => options.heatmap = Some(args.next().ok_or(Self::USAGE)?),
+                "--frames" => options.frames = Some(args.next().ok_or(Self::USAGE)?),
+                "--frame-format" => {
+                    options.frame_format = FrameFormat::parse(&args.next().ok_or(Self::USAGE)?)?;
+                }
+                "--rounds-csv" => options.rounds_csv = Some(args.next().ok_or(Self::USAGE)?),
+                _ if arg.starts_with("--") => return Err(Self::USAGE.into()),
+                _ => options.input_file = arg,
+            }
+        }
+
+        Ok(options)
+    }
+
+    fn load_grid(&self) -> Result<Grid, Box<dyn std::error::Error>> {
+        let grid = match self.alphabet {
+            Some(alphabet) => Grid::from_file_with_alphabet(&self.input_file, alphabet)?,
+            None => Grid::from_file(&self.input_file)?,
+        };
+        Ok(grid
+            .with_neighbourhood(&self.neighbourhood)
+            .with_threshold(self.threshold)
+            .with_boundary(self.boundary)
+            .with_threads(self.threads))
+    }
+
+    fn load_voxels(&self) -> Result<VoxelGrid, Box<dyn std::error::Error>> {
+        let file = File::open(&self.input_file)?;
+        let voxels =
+            VoxelGrid::from_reader(io::BufReader::new(file), self.alphabet.unwrap_or_default())?;
+        Ok(voxels
+            .with_neighbourhood(&self.neighbourhood)?
+            .with_threshold(self.threshold))
+    }
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let input_file = std::env::args()
-        .nth(1)
-        .unwrap_or_else(|| "input.txt".to_string());
+    let options = Options::from_args()?;
+
+    if let Some(size) = options.bench {
+        bitgrid::bench(size, options.threshold);
+        return Ok(());
+    }
+
+    if options.voxels {
+        let voxels = options.load_voxels()?;
+        println!(
+            "Part 1 - Accessible rolls: {}",
+            voxels.count_accessible_rolls()
+        );
+        let mut voxels = options.load_voxels()?;
+        let total_removed = if options.rounds {
+            voxels.count_total_removable_rolls_by_rounds()
+        } else {
+            voxels.count_total_removable_rolls()
+        };
+        println!("Part 2 - Total removable rolls: {}", total_removed);
+        return Ok(());
+    }
+
+    if let Some(spec) = &options.rule {
+        let mut grid = options.load_grid()?;
+        let rule = match spec.as_str() {
+            "removal" => grid.removal_rule(),
+            _ => Rule::parse(spec)?,
+        };
+        let outcome = grid.run(&rule, options.generations);
+        println!("{rule}: {outcome}, {} live cells", grid.count_rolls());
+        return Ok(());
+    }
 
     // Part 1
-    let grid = Grid::from_file(&input_file)?;
-    let accessible = grid.count_accessible_rolls();
+    let grid = options.load_grid()?;
+    let accessible = if options.bitgrid {
+        BitGrid::from_grid(&grid)?.count_accessible_rolls()
+    } else {
+        grid.count_accessible_rolls()
+    };
     println!("Part 1 - Accessible rolls: {}", accessible);
 
     // Part 2
-    let mut grid2 = Grid::from_file(&input_file)?;
-    let total_removed = grid2.count_total_removable_rolls();
+    let mut grid2 = options.load_grid()?;
+    let total_removed = if let Some(dir) = &options.frames {
+        grid2.count_total_removable_rolls_animated(Path::new(dir), options.frame_format)?
+    } else if options.bitgrid {
+        BitGrid::from_grid(&grid2)?.count_total_removable_rolls()
+    } else if options.rounds {
+        grid2.count_total_removable_rolls_by_rounds()
+    } else {
+        grid2.count_total_removable_rolls()
+    };
     println!("Part 2 - Total removable rolls: {}", total_removed);
 
+    for semantics in &options.semantics {
+        let summary = options.load_grid()?.remove_with(semantics)?;
+        println!(
+            "Part 2 ({}) - Total removable rolls: {} in {} passes",
+            semantics.name(),
+            summary.removed,
+            summary.passes
+        );
+    }
+
+    if options.heatmap.is_some() || options.rounds_csv.is_some() {
+        let map = options.load_grid()?.removal_rounds();
+        if let Some(path) = &options.heatmap {
+            map.write_heatmap(path)?;
+        }
+        if let Some(path) = &options.rounds_csv {
+            map.write_csv(&mut io::BufWriter::new(File::create(path)?))?;
+        }
+    }
+
+    if options.core.is_some() || options.components.is_some() {
+        let core = options.load_grid()?.stable_core();
+        if let Some(path) = &options.core {
+            let mut out = io::BufWriter::new(File::create(path)?);
+            core.write(&mut out)?;
+            out.flush()?;
+        }
+        if let Some(connectivity) = options.components {
+            let labelling = core.components(connectivity);
+            println!("Stable core - {} components", labelling.components.len());
+            for (label, component) in labelling.components.iter().enumerate() {
+                println!(
+                    "  {label}: {} rolls, rows {}-{}, cols {}-{}",
+                    component.size,
+                    component.top,
+                    component.bottom,
+                    component.left,
+                    component.right
+                );
+            }
+            if let Some(path) = &options.labels_csv {
+                labelling.write_csv(&mut io::BufWriter::new(File::create(path)?))?;
+            }
+        }
+    }
+
     Ok(())
 }
 
@@ -139,4 +811,276 @@ mod tests {
         let mut grid = Grid::from_file("sample.txt").unwrap();
         assert_eq!(grid.count_total_removable_rolls(), 43);
     }
+
+    #[test]
+    fn test_neighbourhood_offsets() {
+        assert_eq!(Neighbourhood::Moore(1).offsets().len(), 8);
+        assert_eq!(Neighbourhood::Moore(2).offsets().len(), 24);
+        assert_eq!(Neighbourhood::VonNeumann(1).offsets().len(), 4);
+        assert_eq!(Neighbourhood::VonNeumann(2).offsets().len(), 12);
+
+        let kernel = Neighbourhood::from_kernel_file("kernel.txt").unwrap();
+        let mut offsets = kernel.offsets();
+        offsets.sort();
+        assert_eq!(offsets, Neighbourhood::VonNeumann(1).offsets());
+    }
+
+    #[test]
+    fn test_neighbourhood_and_threshold() {
+        let grid = Grid::from_file("sample.txt").unwrap().with_threshold(9);
+        let rolls = grid.cells.iter().flatten().filter(|&&c| c == '@').count();
+        assert_eq!(grid.count_accessible_rolls(), rolls);
+
+        let grid = Grid::from_file("sample.txt")
+            .unwrap()
+            .with_neighbourhood(&Neighbourhood::VonNeumann(1))
+            .with_threshold(0);
+        assert_eq!(grid.count_accessible_rolls(), 0);
+
+        let grid = Grid::from_file("sample.txt")
+            .unwrap()
+            .with_neighbourhood(&Neighbourhood::parse("kernel:kernel.txt").unwrap());
+        let von_neumann = Grid::from_file("sample.txt")
+            .unwrap()
+            .with_neighbourhood(&Neighbourhood::VonNeumann(1));
+        assert_eq!(
+            grid.count_accessible_rolls(),
+            von_neumann.count_accessible_rolls()
+        );
+    }
+
+    #[test]
+    fn test_incremental_matches_rounds() {
+        let neighbourhoods = [
+            Neighbourhood::Moore(1),
+            Neighbourhood::Moore(2),
+            Neighbourhood::VonNeumann(1),
+            Neighbourhood::Custom(vec![(0, 1), (1, 1), (-1, 0)]),
+        ];
+
+        let boundaries = [
+            Boundary::Empty,
+            Boundary::Wall,
+            Boundary::WrapHorizontal,
+            Boundary::WrapBoth,
+        ];
+
+        for path in ["sample.txt", "input.txt"] {
+            for neighbourhood in &neighbourhoods {
+                for threshold in 0..6 {
+                    for boundary in boundaries {
+                        let load = || {
+                            Grid::from_file(path)
+                                .unwrap()
+                                .with_neighbourhood(neighbourhood)
+                                .with_threshold(threshold)
+                                .with_boundary(boundary)
+                        };
+                        let (mut incremental, mut rounds) = (load(), load());
+                        assert_eq!(
+                            incremental.count_total_removable_rolls(),
+                            rounds.count_total_removable_rolls_by_rounds(),
+                            "{path} {neighbourhood:?} threshold {threshold} {boundary:?}"
+                        );
+                        assert_eq!(incremental.cells, rounds.cells);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_removal_rounds() {
+        let mut grid = Grid::from_file("sample.txt").unwrap();
+        let map = grid.removal_rounds();
+        assert_eq!(map.removed(), 43);
+        assert_eq!(map.rounds, 9);
+        assert_eq!(map.cells[0][0], Removal::NoRoll);
+        assert_eq!(map.cells[0][2], Removal::Round(1));
+        assert_eq!(map.cells[4][4], Removal::Never);
+
+        // round r removes exactly what a rescan finds in round r
+        let mut rescan = Grid::from_file("sample.txt").unwrap();
+        for round in 1..=map.rounds {
+            let mut accessible = rescan.find_accessible_positions();
+            let mut expected: Vec<(usize, usize)> = (0..rescan.rows)
+                .flat_map(|row| (0..rescan.cols).map(move |col| (row, col)))
+                .filter(|&(row, col)| map.cells[row][col] == Removal::Round(round))
+                .collect();
+            accessible.sort();
+            expected.sort();
+            assert_eq!(accessible, expected, "round {round}");
+            rescan.remove_rolls(&accessible);
+        }
+        assert!(rescan.find_accessible_positions().is_empty());
+    }
+
+    #[test]
+    fn test_boundary() {
+        let load = |boundary| {
+            Grid::from_reader("@@@\n@@@\n@@@\n".as_bytes())
+                .unwrap()
+                .with_boundary(boundary)
+        };
+
+        let grid = load(Boundary::Empty);
+        assert_eq!(grid.count_adjacent_rolls(0, 0), 3);
+        assert_eq!(grid.count_accessible_rolls(), 4);
+
+        // walls surround every cell with 8 occupied neighbours
+        let grid = load(Boundary::Wall);
+        assert_eq!(grid.count_adjacent_rolls(0, 0), 8);
+        assert_eq!(grid.count_accessible_rolls(), 0);
+
+        let grid = load(Boundary::WrapHorizontal);
+        assert_eq!(grid.count_adjacent_rolls(0, 0), 5);
+        assert_eq!(grid.count_adjacent_rolls(1, 0), 8);
+        assert_eq!(grid.count_accessible_rolls(), 0);
+
+        let grid = load(Boundary::WrapBoth);
+        assert_eq!(grid.neighbour(0, 0, -1, -1), Some((2, 2)));
+        assert_eq!(grid.count_adjacent_rolls(0, 0), 8);
+    }
+
+    #[test]
+    fn test_removal_semantics() {
+        let load = || Grid::from_file("sample.txt").unwrap();
+
+        let simultaneous = load().remove_with(&Semantics::Simultaneous).unwrap();
+        assert_eq!(simultaneous.removed, 43);
+        assert_eq!(simultaneous.passes, 9);
+
+        // removing as soon as a roll is accessible needs fewer passes
+        let mut sequential_grid = load();
+        let sequential = sequential_grid.remove_with(&Semantics::Sequential).unwrap();
+        assert_eq!(sequential.removed, 43);
+        assert!(sequential.passes < simultaneous.passes);
+
+        // bottom-up priority peels in yet another order
+        let bottom_up: Vec<(usize, usize)> = (0..10)
+            .rev()
+            .flat_map(|row| (0..10).map(move |col| (row, col)))
+            .collect();
+        let mut priority_grid = load();
+        let priority = priority_grid
+            .remove_with(&Semantics::Priority(bottom_up))
+            .unwrap();
+        assert_eq!(priority.removed, 43);
+        assert_eq!(priority_grid.cells, sequential_grid.cells);
+
+        assert!(
+            load()
+                .remove_with(&Semantics::Priority(vec![(10, 0)]))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_removal_as_automaton() {
+        for boundary in [Boundary::Empty, Boundary::Wall] {
+            let mut grid = Grid::from_file("input.txt")
+                .unwrap()
+                .with_boundary(boundary);
+            let rolls = grid.count_rolls();
+            let rule = grid.removal_rule();
+            let outcome = grid.run(&rule, 1000);
+            assert!(matches!(outcome, automaton::Outcome::FixedPoint { .. }));
+
+            let mut peeled = Grid::from_file("input.txt")
+                .unwrap()
+                .with_boundary(boundary);
+            assert_eq!(
+                rolls - grid.count_rolls(),
+                peeled.count_total_removable_rolls()
+            );
+            assert_eq!(grid.cells, peeled.cells);
+        }
+    }
+
+    #[test]
+    fn test_validated_parsing() {
+        let error = Grid::from_reader("@@.\n@.\n...\n".as_bytes())
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "line 2: expected 3 cells like line 1, found 2"
+        );
+
+        let error = Grid::from_reader("@@.\n@x.\n".as_bytes()).err().unwrap();
+        assert_eq!(error.to_string(), "line 2, column 2: unknown symbol 'x'");
+
+        // trailing blank lines and CRLF endings are fine
+        let grid = Grid::from_reader("@@.\r\n@..\r\n\n\n".as_bytes()).unwrap();
+        assert_eq!((grid.rows, grid.cols), (2, 3));
+
+        let alphabet = Alphabet::parse("R_X").unwrap();
+        let grid = Grid::from_reader_with_alphabet("R_X\n__R\n".as_bytes(), alphabet).unwrap();
+        assert_eq!(grid.cells, vec![vec!['@', '.', '#'], vec!['.', '.', '@']]);
+        let mut out = Vec::new();
+        grid.write(&mut out).unwrap();
+        assert_eq!(out, b"R_X\n__R\n");
+
+        assert!(Alphabet::parse("@@#").is_err());
+        assert!(Alphabet::parse("@.").is_err());
+    }
+
+    #[test]
+    fn test_obstacles() {
+        // the centre roll is boxed in by obstacles, which never go
+        let mut grid = Grid::from_reader("###\n#@#\n###\n".as_bytes()).unwrap();
+        assert_eq!(grid.count_adjacent_rolls(1, 1), 8);
+        assert_eq!(grid.count_accessible_rolls(), 0);
+        assert_eq!(grid.count_total_removable_rolls(), 0);
+
+        let mut grid = Grid::from_reader("#@@\n.@.\n...\n".as_bytes()).unwrap();
+        let map = grid.removal_rounds();
+        assert_eq!(map.cells[0][0], Removal::Obstacle);
+        assert_eq!(map.removed(), 3);
+        assert_eq!(grid.cells[0][0], '#');
+
+        let mut rounds = Grid::from_reader("#@@#\n@@@@\n#@@#\n".as_bytes()).unwrap();
+        let mut incremental = rounds.clone();
+        assert_eq!(
+            rounds.count_total_removable_rolls_by_rounds(),
+            incremental.count_total_removable_rolls()
+        );
+        assert_eq!(rounds.cells, incremental.cells);
+    }
+
+    #[test]
+    fn test_parallel_matches_serial() {
+        for boundary in [Boundary::Empty, Boundary::Wall, Boundary::WrapBoth] {
+            let serial = Grid::from_file("input.txt")
+                .unwrap()
+                .with_boundary(boundary);
+            let accessible = serial.find_accessible_positions();
+            let mut serial_rounds = serial.clone();
+            let removed = serial_rounds.count_total_removable_rolls_by_rounds();
+
+            for threads in [2, 7, 500] {
+                let parallel = serial.clone().with_threads(threads);
+                assert_eq!(parallel.count_accessible_rolls(), accessible.len());
+                assert_eq!(parallel.find_accessible_positions(), accessible);
+
+                let mut parallel_rounds = parallel.clone();
+                assert_eq!(
+                    parallel_rounds.count_total_removable_rolls_by_rounds(),
+                    removed
+                );
+                assert_eq!(parallel_rounds.cells, serial_rounds.cells);
+
+                let mut parallel_peel = parallel.clone();
+                assert_eq!(parallel_peel.count_total_removable_rolls(), removed);
+                assert_eq!(parallel_peel.cells, serial_rounds.cells);
+                assert_eq!(
+                    parallel.clone().removal_rounds(),
+                    serial.clone().removal_rounds()
+                );
+            }
+        }
+
+        let empty = Grid::from_reader("".as_bytes()).unwrap().with_threads(4);
+        assert_eq!(empty.count_accessible_rolls(), 0);
+    }
 }
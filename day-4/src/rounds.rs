@@ -0,0 +1,165 @@
+use std::fs::File;
+use std::io::{self, Write};
+
+use crate::Grid;
+
+/// What happened to the roll in a cell while peeling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Removal {
+    /// The cell never held a roll.
+    NoRoll,
+    /// The cell holds a fixed obstacle.
+    Obstacle,
+    /// The roll was removed in this round, starting from 1.
+    Round(usize),
+    /// The roll is part of the stable core and is never removed.
+    Never,
+}
+
+/// The removal round of every cell of a grid.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RoundMap {
+    pub cells: Vec<Vec<Removal>>,
+    // number of rounds until nothing more was accessible
+    pub rounds: usize,
+}
+
+impl RoundMap {
+    /// A map with every roll of `grid` marked as never removed.
+    pub fn new(grid: &Grid) -> Self {
+        let cells = grid
+            .cells
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&cell| match cell {
+                        '@' => Removal::Never,
+                        '#' => Removal::Obstacle,
+                        _ => Removal::NoRoll,
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self { cells, rounds: 0 }
+    }
+
+    pub fn removed(&self) -> usize {
+        self.cells
+            .iter()
+            .flatten()
+            .filter(|removal| matches!(removal, Removal::Round(_)))
+            .count()
+    }
+
+    /// Writes one `row,col,round` line per roll, with `never` for the
+    /// rolls of the stable core.
+    pub fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "row,col,round")?;
+        for (row, cells) in self.cells.iter().enumerate() {
+            for (col, removal) in cells.iter().enumerate() {
+                match removal {
+                    Removal::NoRoll | Removal::Obstacle => {}
+                    Removal::Round(round) => writeln!(out, "{row},{col},{round}")?,
+                    Removal::Never => writeln!(out, "{row},{col},never")?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Brightness of a cell: black floor, dark grey obstacles, early
+    /// rounds dark through to late rounds bright, and the stable core
+    /// white.
+    fn level(&self, removal: Removal) -> u8 {
+        match removal {
+            Removal::NoRoll => 0,
+            Removal::Obstacle => 16,
+            Removal::Round(round) => {
+                let span = self.rounds.saturating_sub(1).max(1);
+                (32 + (round - 1) * 191 / span) as u8
+            }
+            Removal::Never => 255,
+        }
+    }
+
+    /// Writes a binary greyscale (P5) PGM heatmap.
+    pub fn write_pgm(&self, out: &mut impl Write) -> io::Result<()> {
+        let (rows, cols) = (self.cells.len(), self.cells.first().map_or(0, Vec::len));
+        write!(out, "P5\n{cols} {rows}\n255\n")?;
+        for cells in &self.cells {
+            let levels: Vec<u8> = cells.iter().map(|&removal| self.level(removal)).collect();
+            out.write_all(&levels)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a binary colour (P6) PPM heatmap running from blue for
+    /// the first round to red for the last, with the stable core white.
+    pub fn write_ppm(&self, out: &mut impl Write) -> io::Result<()> {
+        let (rows, cols) = (self.cells.len(), self.cells.first().map_or(0, Vec::len));
+        write!(out, "P6\n{cols} {rows}\n255\n")?;
+        for cells in &self.cells {
+            for &removal in cells {
+                let pixel = match removal {
+                    Removal::NoRoll => [0, 0, 0],
+                    Removal::Obstacle => [64, 64, 64],
+                    Removal::Round(_) => {
+                        let level = self.level(removal);
+                        [level, 0, 255 - level]
+                    }
+                    Removal::Never => [255, 255, 255],
+                };
+                out.write_all(&pixel)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes a PGM or PPM heatmap depending on the file extension.
+    pub fn write_heatmap(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut out = io::BufWriter::new(File::create(path)?);
+        if path.ends_with(".pgm") {
+            self.write_pgm(&mut out)?;
+        } else if path.ends_with(".ppm") {
+            self.write_ppm(&mut out)?;
+        } else {
+            return Err(format!("Heatmap must be a .pgm or .ppm file: {path}").into());
+        }
+        out.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exports() {
+        let map = RoundMap {
+            cells: vec![vec![
+                Removal::NoRoll,
+                Removal::Round(1),
+                Removal::Round(2),
+                Removal::Never,
+            ]],
+            rounds: 2,
+        };
+
+        let mut csv = Vec::new();
+        map.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "row,col,round\n0,1,1\n0,2,2\n0,3,never\n"
+        );
+
+        let mut pgm = Vec::new();
+        map.write_pgm(&mut pgm).unwrap();
+        assert_eq!(pgm, b"P5\n4 1\n255\n\x00\x20\xdf\xff");
+
+        let mut ppm = Vec::new();
+        map.write_ppm(&mut ppm).unwrap();
+        assert_eq!(ppm.len(), "P6\n4 1\n255\n".len() + 12);
+    }
+}
@@ -0,0 +1,132 @@
+use std::fs::File;
+use std::io::{self, BufRead};
+
+use crate::Grid;
+
+/// When a roll that has become accessible is taken away.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Semantics {
+    /// All rolls accessible at the start of a round go together.
+    Simultaneous,
+    /// Rolls are visited in row-major order and each accessible one is
+    /// taken away at once, before the next is examined.
+    Sequential,
+    /// Like `Sequential`, but visiting the given (row, col) positions
+    /// first, in order, and the rest in row-major order.
+    Priority(Vec<(usize, usize)>),
+}
+
+impl Semantics {
+    /// Parses `simultaneous`, `sequential` or `priority:<file>`, where
+    /// the file holds one `row,col` position per line.
+    pub fn parse(spec: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        match spec.split_once(':') {
+            None if spec == "simultaneous" => Ok(Semantics::Simultaneous),
+            None if spec == "sequential" => Ok(Semantics::Sequential),
+            Some(("priority", path)) => Self::from_priority_file(path),
+            _ => Err(format!("Unknown removal semantics: {spec}").into()),
+        }
+    }
+
+    fn from_priority_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let file = File::open(path)?;
+        let mut order = Vec::new();
+
+        for (i, line) in io::BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (row, col) = line
+                .split_once(',')
+                .ok_or_else(|| format!("{path}:{}: expected row,col: {line}", i + 1))?;
+            order.push((row.trim().parse()?, col.trim().parse()?));
+        }
+
+        Ok(Semantics::Priority(order))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Semantics::Simultaneous => "simultaneous",
+            Semantics::Sequential => "sequential",
+            Semantics::Priority(_) => "priority",
+        }
+    }
+}
+
+/// Outcome of removing rolls until none are accessible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RemovalSummary {
+    pub removed: usize,
+    // rounds, or passes over the grid, that removed at least one roll
+    pub passes: usize,
+}
+
+impl Grid {
+    /// Removes rolls under the given semantics until none are
+    /// accessible.
+    ///
+    /// Taking a roll away never makes another one less accessible, so
+    /// every semantics peels down to the same stable core and removes
+    /// the same total; they differ in which rolls go in which pass and
+    /// in how many passes it takes.
+    pub fn remove_with(
+        &mut self,
+        semantics: &Semantics,
+    ) -> Result<RemovalSummary, Box<dyn std::error::Error>> {
+        let row_major = (0..self.rows).flat_map(|row| (0..self.cols).map(move |col| (row, col)));
+
+        let order: Vec<(usize, usize)> = match semantics {
+            Semantics::Simultaneous => {
+                let mut removed = 0;
+                let passes = self.peel(|_, _, _| removed += 1);
+                return Ok(RemovalSummary { removed, passes });
+            }
+            Semantics::Sequential => row_major.collect(),
+            Semantics::Priority(priority) => {
+                let mut seen = vec![false; self.rows * self.cols];
+                let mut order = Vec::with_capacity(self.rows * self.cols);
+                for (row, col) in priority.iter().copied().chain(row_major) {
+                    if row >= self.rows || col >= self.cols {
+                        return Err(format!("Priority position {row},{col} is off the grid").into());
+                    }
+                    if !seen[row * self.cols + col] {
+                        seen[row * self.cols + col] = true;
+                        order.push((row, col));
+                    }
+                }
+                order
+            }
+        };
+
+        Ok(self.remove_in_order(&order))
+    }
+
+    fn remove_in_order(&mut self, order: &[(usize, usize)]) -> RemovalSummary {
+        let mut summary = RemovalSummary {
+            removed: 0,
+            passes: 0,
+        };
+
+        loop {
+            let mut removed = 0;
+            for &(row, col) in order {
+                if self.is_accessible(row, col) {
+                    self.cells[row][col] = '.';
+                    removed += 1;
+                }
+            }
+            if removed == 0 {
+                break;
+            }
+
+            summary.removed += removed;
+            summary.passes += 1;
+        }
+
+        summary
+    }
+}
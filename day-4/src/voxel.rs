@@ -0,0 +1,278 @@
+use std::io::BufRead;
+
+use crate::{Alphabet, Grid, Neighbourhood};
+
+/// A (layer, row, col) offset.
+type Offset = (isize, isize, isize);
+
+/// A three-dimensional counterpart of `Grid` for high-bay storage,
+/// indexed by (layer, row, col).  Off-grid cells are empty.
+pub struct VoxelGrid {
+    // layer-major, then row-major
+    cells: Vec<char>,
+    layers: usize,
+    rows: usize,
+    cols: usize,
+    offsets: Vec<Offset>,
+    threshold: usize,
+}
+
+impl VoxelGrid {
+    /// Reads 2D layers separated by blank lines, each in the same
+    /// format as `Grid` and all of the same size.
+    pub fn from_reader(
+        reader: impl BufRead,
+        alphabet: Alphabet,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut layers: Vec<Vec<String>> = vec![Vec::new()];
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim_end_matches('\r').is_empty() {
+                if layers.last().is_some_and(|layer| !layer.is_empty()) {
+                    layers.push(Vec::new());
+                }
+            } else {
+                layers.last_mut().unwrap().push(line);
+            }
+        }
+        if layers.last().is_some_and(Vec::is_empty) {
+            layers.pop();
+        }
+
+        let mut voxels = Self {
+            cells: Vec::new(),
+            layers: layers.len(),
+            rows: 0,
+            cols: 0,
+            offsets: Self::offsets(&Neighbourhood::default())?,
+            threshold: Grid::DEFAULT_THRESHOLD,
+        };
+        for (i, lines) in layers.iter().enumerate() {
+            let layer = Grid::from_reader_with_alphabet(lines.join("\n").as_bytes(), alphabet)
+                .map_err(|e| format!("layer {}: {e}", i + 1))?;
+            if i == 0 {
+                (voxels.rows, voxels.cols) = (layer.rows, layer.cols);
+            } else if (layer.rows, layer.cols) != (voxels.rows, voxels.cols) {
+                return Err(format!(
+                    "layer {}: expected {}x{} cells like layer 1, found {}x{}",
+                    i + 1,
+                    voxels.rows,
+                    voxels.cols,
+                    layer.rows,
+                    layer.cols
+                )
+                .into());
+            }
+            voxels.cells.extend(layer.cells.into_iter().flatten());
+        }
+
+        Ok(voxels)
+    }
+
+    /// The 3D offsets of a Moore or von Neumann neighbourhood; Moore(1)
+    /// gives the default 26 neighbours and von Neumann(1) the 6 face
+    /// neighbours.
+    fn offsets(neighbourhood: &Neighbourhood) -> Result<Vec<Offset>, Box<dyn std::error::Error>> {
+        let (radius, manhattan) = match neighbourhood {
+            Neighbourhood::Moore(radius) => (*radius as isize, false),
+            Neighbourhood::VonNeumann(radius) => (*radius as isize, true),
+            Neighbourhood::Custom(_) => {
+                return Err("Kernel neighbourhoods are 2D only".into());
+            }
+        };
+
+        let mut offsets = Vec::new();
+        for dl in -radius..=radius {
+            for dr in -radius..=radius {
+                for dc in -radius..=radius {
+                    let within = !manhattan || dl.abs() + dr.abs() + dc.abs() <= radius;
+                    if within && (dl, dr, dc) != (0, 0, 0) {
+                        offsets.push((dl, dr, dc));
+                    }
+                }
+            }
+        }
+        Ok(offsets)
+    }
+
+    pub fn with_neighbourhood(
+        mut self,
+        neighbourhood: &Neighbourhood,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        self.offsets = Self::offsets(neighbourhood)?;
+        Ok(self)
+    }
+
+    pub fn with_threshold(mut self, threshold: usize) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Index of the cell at the given offset from `index`, if it is on
+    /// the grid.
+    fn neighbour(&self, index: usize, (dl, dr, dc): Offset) -> Option<usize> {
+        let col = (index % self.cols) as isize + dc;
+        let row = (index / self.cols % self.rows) as isize + dr;
+        let layer = (index / (self.cols * self.rows)) as isize + dl;
+
+        if layer < 0
+            || layer >= self.layers as isize
+            || row < 0
+            || row >= self.rows as isize
+            || col < 0
+            || col >= self.cols as isize
+        {
+            return None;
+        }
+        Some((layer as usize * self.rows + row as usize) * self.cols + col as usize)
+    }
+
+    /// Counts the rolls and obstacles around a cell.
+    fn count_adjacent_rolls(&self, index: usize) -> usize {
+        self.offsets
+            .iter()
+            .filter_map(|&offset| self.neighbour(index, offset))
+            .filter(|&neighbour| self.cells[neighbour] != '.')
+            .count()
+    }
+
+    fn is_accessible(&self, index: usize) -> bool {
+        self.cells[index] == '@' && self.count_adjacent_rolls(index) < self.threshold
+    }
+
+    pub fn count_accessible_rolls(&self) -> usize {
+        (0..self.cells.len())
+            .filter(|&index| self.is_accessible(index))
+            .count()
+    }
+
+    /// Removes all accessible rolls together, round after round, until
+    /// none are accessible.  Rescans the whole grid every round.
+    pub fn count_total_removable_rolls_by_rounds(&mut self) -> usize {
+        let mut total_removed = 0;
+
+        loop {
+            let accessible: Vec<usize> = (0..self.cells.len())
+                .filter(|&index| self.is_accessible(index))
+                .collect();
+            if accessible.is_empty() {
+                break;
+            }
+
+            total_removed += accessible.len();
+            for index in accessible {
+                self.cells[index] = '.';
+            }
+        }
+
+        total_removed
+    }
+
+    /// Removes rolls until none are accessible, keeping every roll's
+    /// neighbour count up to date and queueing only those that drop
+    /// below the threshold, as `Grid::removal_rounds` does in 2D.
+    pub fn count_total_removable_rolls(&mut self) -> usize {
+        let mut counts = vec![0; self.cells.len()];
+        let mut queued = vec![false; self.cells.len()];
+        let mut queue = Vec::new();
+
+        for index in 0..self.cells.len() {
+            if self.cells[index] == '@' {
+                counts[index] = self.count_adjacent_rolls(index);
+                if counts[index] < self.threshold {
+                    queued[index] = true;
+                    queue.push(index);
+                }
+            }
+        }
+
+        let mut total_removed = 0;
+        while let Some(index) = queue.pop() {
+            self.cells[index] = '.';
+            total_removed += 1;
+
+            for &(dl, dr, dc) in &self.offsets {
+                let Some(neighbour) = self.neighbour(index, (-dl, -dr, -dc)) else {
+                    continue;
+                };
+                if self.cells[neighbour] != '@' || queued[neighbour] {
+                    continue;
+                }
+                counts[neighbour] -= 1;
+                if counts[neighbour] < self.threshold {
+                    queued[neighbour] = true;
+                    queue.push(neighbour);
+                }
+            }
+        }
+
+        total_removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io;
+
+    fn load(path: &str) -> VoxelGrid {
+        let file = File::open(path).unwrap();
+        VoxelGrid::from_reader(io::BufReader::new(file), Alphabet::default()).unwrap()
+    }
+
+    #[test]
+    fn test_parse_layers() {
+        let voxels = load("sample3d.txt");
+        assert_eq!((voxels.layers, voxels.rows, voxels.cols), (3, 5, 5));
+        assert_eq!(voxels.offsets.len(), 26);
+        assert_eq!(voxels.count_accessible_rolls(), 8);
+
+        let error = VoxelGrid::from_reader("@@\n@@\n\n@@@\n@@@\n".as_bytes(), Alphabet::default())
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "layer 2: expected 2x2 cells like layer 1, found 2x3"
+        );
+    }
+
+    #[test]
+    fn test_single_layer_matches_grid() {
+        let mut grid = Grid::from_file("sample.txt").unwrap();
+        let mut voxels = load("sample.txt");
+        assert_eq!(
+            voxels.count_accessible_rolls(),
+            grid.count_accessible_rolls()
+        );
+        assert_eq!(
+            voxels.count_total_removable_rolls(),
+            grid.count_total_removable_rolls()
+        );
+    }
+
+    #[test]
+    fn test_incremental_matches_rounds() {
+        for neighbourhood in [
+            Neighbourhood::Moore(1),
+            Neighbourhood::VonNeumann(1),
+            Neighbourhood::Moore(2),
+        ] {
+            for threshold in [3, 4, 6, 10] {
+                let mut rounds = load("sample3d.txt")
+                    .with_neighbourhood(&neighbourhood)
+                    .unwrap()
+                    .with_threshold(threshold);
+                let mut incremental = load("sample3d.txt")
+                    .with_neighbourhood(&neighbourhood)
+                    .unwrap()
+                    .with_threshold(threshold);
+                assert_eq!(
+                    rounds.count_total_removable_rolls_by_rounds(),
+                    incremental.count_total_removable_rolls()
+                );
+                assert_eq!(rounds.cells, incremental.cells);
+            }
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::BTreeMap;
+
+use crate::Range;
+
+/// An interval set that ranges can be added to and retracted from.
+///
+/// Intervals are kept disjoint and non-adjacent in a map from start to
+/// end, with a running total of the IDs they hold.
+#[derive(Debug, Clone, Default)]
+pub struct DynamicIntervalSet {
+    intervals: BTreeMap<usize, usize>,
+    // wide enough for a set holding every usize
+    total: u128,
+}
+
+impl DynamicIntervalSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds every ID in `range`, merging with intervals it overlaps or
+    /// touches.
+    pub fn insert(&mut self, range: Range) {
+        if range.start > range.end {
+            return;
+        }
+
+        let (mut start, mut end) = (range.start, range.end);
+        if let Some((&s, &e)) = self.intervals.range(..start).next_back()
+            && e >= start - 1
+        {
+            start = s;
+        }
+
+        let touching: Vec<(usize, usize)> = self
+            .intervals
+            .range(start..=end.saturating_add(1))
+            .map(|(&s, &e)| (s, e))
+            .collect();
+        for (s, e) in touching {
+            self.take(s, e);
+            end = end.max(e);
+        }
+
+        self.put(start, end);
+    }
+
+    /// Retracts every ID in `range`, splitting intervals that stick out
+    /// either side of it.
+    pub fn remove(&mut self, range: Range) {
+        if range.start > range.end {
+            return;
+        }
+
+        let overlapping: Vec<(usize, usize)> = self
+            .intervals
+            .range(..range.start)
+            .next_back()
+            .filter(|&(_, &e)| e >= range.start)
+            .into_iter()
+            .chain(self.intervals.range(range.start..=range.end))
+            .map(|(&s, &e)| (s, e))
+            .collect();
+        for (s, e) in overlapping {
+            self.take(s, e);
+            if s < range.start {
+                self.put(s, range.start - 1);
+            }
+            if e > range.end {
+                self.put(range.end + 1, e);
+            }
+        }
+    }
+
+    fn put(&mut self, start: usize, end: usize) {
+        self.intervals.insert(start, end);
+        self.total += (end - start) as u128 + 1;
+    }
+
+    fn take(&mut self, start: usize, end: usize) {
+        self.intervals.remove(&start);
+        self.total -= (end - start) as u128 + 1;
+    }
+
+    pub fn contains(&self, id: usize) -> bool {
+        self.intervals
+            .range(..=id)
+            .next_back()
+            .is_some_and(|(_, &end)| end >= id)
+    }
+
+    /// Number of IDs in the set.
+    pub fn len(&self) -> u128 {
+        self.total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_and_merge() {
+        let mut set = DynamicIntervalSet::new();
+        set.insert(Range { start: 3, end: 5 });
+        set.insert(Range { start: 10, end: 20 });
+        set.insert(Range { start: 6, end: 6 });
+        assert_eq!(set.intervals, BTreeMap::from([(3, 6), (10, 20)]));
+        assert_eq!(set.len(), 15);
+
+        set.remove(Range { start: 12, end: 14 });
+        assert_eq!(set.intervals, BTreeMap::from([(3, 6), (10, 11), (15, 20)]));
+        assert_eq!(set.len(), 12);
+        assert!(!set.contains(13));
+        assert!(set.contains(15));
+
+        set.remove(Range { start: 0, end: 10 });
+        set.insert(Range { start: 9, end: 2 });
+        assert_eq!(set.intervals, BTreeMap::from([(11, 11), (15, 20)]));
+        assert_eq!(set.len(), 7);
+    }
+
+    #[test]
+    fn test_whole_id_space() {
+        let mut set = DynamicIntervalSet::new();
+        set.insert(Range {
+            start: 0,
+            end: usize::MAX,
+        });
+        assert_eq!(set.len(), 1 << 64);
+        assert!(set.contains(usize::MAX));
+
+        set.remove(Range { start: 5, end: 5 });
+        assert_eq!(set.len(), (1 << 64) - 1);
+        set.remove(Range { start: 6, end: 6 });
+        assert_eq!(set.len(), (1 << 64) - 2);
+
+        // Adjacent halves merge into the whole space
+        let mut set = DynamicIntervalSet::new();
+        set.insert(Range {
+            start: 100,
+            end: usize::MAX,
+        });
+        set.insert(Range { start: 0, end: 99 });
+        assert_eq!(set.intervals, BTreeMap::from([(0, usize::MAX)]));
+        set.remove(Range {
+            start: 0,
+            end: usize::MAX,
+        });
+        assert_eq!(set.len(), 0);
+        assert!(set.intervals.is_empty());
+    }
+
+    #[test]
+    fn test_matches_bitmap() {
+        const SIZE: usize = 200;
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as usize % bound
+        };
+
+        let mut set = DynamicIntervalSet::new();
+        let mut bitmap = [false; SIZE];
+
+        for _ in 0..5000 {
+            let start = next(SIZE);
+            let end = (start + next(30)).min(SIZE - 1);
+            let insert = next(3) != 0;
+
+            if insert {
+                set.insert(Range { start, end });
+            } else {
+                set.remove(Range { start, end });
+            }
+            bitmap[start..=end].fill(insert);
+
+            let fresh = bitmap.iter().filter(|&&fresh| fresh).count();
+            assert_eq!(set.len(), fresh as u128);
+            for (id, &fresh) in bitmap.iter().enumerate() {
+                assert_eq!(set.contains(id), fresh, "id {id}");
+            }
+
+            // Intervals stay disjoint and non-adjacent
+            let intervals: Vec<(usize, usize)> =
+                set.intervals.iter().map(|(&s, &e)| (s, e)).collect();
+            for pair in intervals.windows(2) {
+                assert!(pair[0].1 + 1 < pair[1].0, "{pair:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::io::{self, Write};
+
+use crate::Range;
+use crate::interval_set::IntervalSet;
+
+/// The spoiled stretches between fresh ranges within a universe.
+#[derive(Debug)]
+pub struct GapReport {
+    pub universe: Range,
+    pub gaps: Vec<Range>,
+}
+
+impl GapReport {
+    /// Finds the gaps in `universe`, or between the lowest and highest
+    /// fresh IDs if none is given.
+    pub fn new(
+        fresh: &IntervalSet,
+        universe: Option<Range>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let universe = match universe {
+            Some(universe) => universe,
+            None => fresh
+                .span()
+                .ok_or("No fresh ranges to take the universe from")?,
+        };
+
+        let gaps = fresh.complement(universe).iter().copied().collect();
+        Ok(Self { universe, gaps })
+    }
+
+    /// The widest gap, the first one if several tie.
+    pub fn largest(&self) -> Option<Range> {
+        self.gaps
+            .iter()
+            .copied()
+            .reduce(|best, gap| if gap.size() > best.size() { gap } else { best })
+    }
+
+    /// Number of spoiled IDs across all gaps.
+    pub fn spoiled(&self) -> u128 {
+        self.gaps.iter().map(Range::size).sum()
+    }
+
+    pub fn write_text(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "{} gaps in {}, {} spoiled IDs",
+            self.gaps.len(),
+            self.universe,
+            self.spoiled()
+        )?;
+        for gap in &self.gaps {
+            writeln!(out, "  {gap}: {}", gap.size())?;
+        }
+        if let Some(largest) = self.largest() {
+            writeln!(out, "largest gap: {largest} ({} IDs)", largest.size())?;
+        }
+        Ok(())
+    }
+
+    pub fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "start,end,size")?;
+        for gap in &self.gaps {
+            writeln!(out, "{},{},{}", gap.start, gap.end, gap.size())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> IntervalSet {
+        IntervalSet::from_ranges(vec![
+            Range { start: 3, end: 5 },
+            Range { start: 10, end: 14 },
+            Range { start: 22, end: 23 },
+        ])
+    }
+
+    #[test]
+    fn test_report() {
+        let report = GapReport::new(&sample(), None).unwrap();
+        assert_eq!(report.universe, Range { start: 3, end: 23 });
+        assert_eq!(
+            report.gaps,
+            vec![Range { start: 6, end: 9 }, Range { start: 15, end: 21 }]
+        );
+        assert_eq!(report.largest(), Some(Range { start: 15, end: 21 }));
+        assert_eq!(report.spoiled(), 11);
+
+        let mut text = Vec::new();
+        report.write_text(&mut text).unwrap();
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "2 gaps in 3-23, 11 spoiled IDs\n  6-9: 4\n  15-21: 7\nlargest gap: 15-21 (7 IDs)\n"
+        );
+
+        let mut csv = Vec::new();
+        report.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "start,end,size\n6,9,4\n15,21,7\n"
+        );
+
+        let universe = Range { start: 0, end: 9 };
+        let report = GapReport::new(&sample(), Some(universe)).unwrap();
+        assert_eq!(
+            report.gaps,
+            vec![Range { start: 0, end: 2 }, Range { start: 6, end: 9 }]
+        );
+        assert_eq!(report.largest(), Some(Range { start: 6, end: 9 }));
+
+        assert!(GapReport::new(&IntervalSet::default(), None).is_err());
+
+        // A gap over every ID
+        let everything = Range {
+            start: 0,
+            end: usize::MAX,
+        };
+        let report = GapReport::new(&IntervalSet::default(), Some(everything)).unwrap();
+        assert_eq!(report.largest(), Some(everything));
+        assert_eq!(report.spoiled(), 1 << 64);
+        let mut csv = Vec::new();
+        report.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "start,end,size\n0,18446744073709551615,18446744073709551616\n"
+        );
+    }
+}
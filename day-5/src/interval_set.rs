@@ -0,0 +1,295 @@
+use crate::Range;
+
+/// A set of IDs stored as sorted, disjoint, non-adjacent ranges.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IntervalSet {
+    intervals: Vec<Range>,
+    // running total of IDs: sizes[i] counts intervals[0..=i], wide
+    // enough for a set that covers every ID
+    sizes: Vec<u128>,
+}
+
+impl IntervalSet {
+    fn new(intervals: Vec<Range>) -> Self {
+        let sizes = intervals
+            .iter()
+            .scan(0, |total, r| {
+                *total += r.size();
+                Some(*total)
+            })
+            .collect();
+        Self { intervals, sizes }
+    }
+
+    /// Sorts the ranges and merges those that overlap or touch.
+    /// Reversed ranges hold no IDs and are dropped.
+    pub fn from_ranges(mut ranges: Vec<Range>) -> Self {
+        ranges.retain(|r| r.start <= r.end);
+
+        // Sort ranges by start value
+        ranges.sort_by_key(|r| r.start);
+
+        // Merge overlapping or adjacent ranges
+        let mut intervals: Vec<Range> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match intervals.last_mut() {
+                // If ranges overlap or are adjacent (end + 1 >= start), merge them
+                Some(current) if current.end >= range.start.saturating_sub(1) => {
+                    current.end = current.end.max(range.end);
+                }
+                _ => intervals.push(range),
+            }
+        }
+
+        Self::new(intervals)
+    }
+
+    /// Binary searches for the interval that could hold `id`.
+    pub fn contains(&self, id: usize) -> bool {
+        let i = self.intervals.partition_point(|r| r.end < id);
+        self.intervals.get(i).is_some_and(|r| r.contains(id))
+    }
+
+    /// Number of IDs in the set.
+    pub fn len(&self) -> u128 {
+        self.sizes.last().copied().unwrap_or(0)
+    }
+
+    /// The range from the lowest ID in the set to the highest.
+    pub fn span(&self) -> Option<Range> {
+        let first = self.intervals.first()?;
+        let last = self.intervals.last()?;
+        Some(Range {
+            start: first.start,
+            end: last.end,
+        })
+    }
+
+    /// Number of IDs in the set that fall within `range`.
+    pub fn count_in(&self, range: Range) -> u128 {
+        if range.start > range.end {
+            return 0;
+        }
+
+        // intervals[first..last] are the ones overlapping the range
+        let first = self.intervals.partition_point(|r| r.end < range.start);
+        let last = self.intervals.partition_point(|r| r.start <= range.end);
+        if first >= last {
+            return 0;
+        }
+
+        let before = if first > 0 { self.sizes[first - 1] } else { 0 };
+        let mut count = self.sizes[last - 1] - before;
+
+        // Trim the intervals that stick out either end
+        count -= range.start.saturating_sub(self.intervals[first].start) as u128;
+        count -= self.intervals[last - 1].end.saturating_sub(range.end) as u128;
+        count
+    }
+
+    /// IDs in either set.
+    pub fn union(&self, other: &Self) -> Self {
+        Self::from_ranges(self.iter().chain(other.iter()).copied().collect())
+    }
+
+    /// IDs in both sets.
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut intervals = Vec::new();
+        let (mut i, mut j) = (0, 0);
+
+        while let (Some(a), Some(b)) = (self.intervals.get(i), other.intervals.get(j)) {
+            let start = a.start.max(b.start);
+            let end = a.end.min(b.end);
+            if start <= end {
+                intervals.push(Range { start, end });
+            }
+
+            // Advance whichever interval finishes first
+            if a.end < b.end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+
+        Self::new(intervals)
+    }
+
+    /// IDs in this set but not in `other`.
+    pub fn difference(&self, other: &Self) -> Self {
+        let everything = Range {
+            start: 0,
+            end: usize::MAX,
+        };
+        self.intersection(&other.complement(everything))
+    }
+
+    /// IDs in exactly one of the sets.
+    pub fn symmetric_difference(&self, other: &Self) -> Self {
+        self.difference(other).union(&other.difference(self))
+    }
+
+    /// IDs in `universe` that are not in the set.
+    pub fn complement(&self, universe: Range) -> Self {
+        let mut intervals = Vec::new();
+        let mut next = universe.start;
+
+        for r in self.iter() {
+            if r.end < universe.start {
+                continue;
+            }
+            if r.start > universe.end {
+                break;
+            }
+            if r.start > next {
+                intervals.push(Range {
+                    start: next,
+                    end: r.start - 1,
+                });
+            }
+            if r.end >= universe.end {
+                return Self::new(intervals);
+            }
+            next = r.end + 1;
+        }
+
+        if next <= universe.end {
+            intervals.push(Range {
+                start: next,
+                end: universe.end,
+            });
+        }
+
+        Self::new(intervals)
+    }
+
+    /// A cursor for checking IDs given in increasing order.
+    pub fn sweep(&self) -> Sweep<'_> {
+        Sweep {
+            intervals: &self.intervals,
+        }
+    }
+
+    /// The merged intervals in increasing order.
+    pub fn iter(&self) -> std::slice::Iter<'_, Range> {
+        self.intervals.iter()
+    }
+}
+
+/// Answers `contains` for IDs given in increasing order by walking the
+/// intervals alongside them, instead of searching for each one.
+pub struct Sweep<'a> {
+    // intervals that end at or after the last ID checked
+    intervals: &'a [Range],
+}
+
+impl Sweep<'_> {
+    pub fn contains(&mut self, id: usize) -> bool {
+        while let Some((first, rest)) = self.intervals.split_first()
+            && first.end < id
+        {
+            self.intervals = rest;
+        }
+        self.intervals.first().is_some_and(|r| r.contains(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(pairs: &[(usize, usize)]) -> Vec<Range> {
+        pairs
+            .iter()
+            .map(|&(start, end)| Range { start, end })
+            .collect()
+    }
+
+    #[test]
+    fn test_merge() {
+        let set = IntervalSet::from_ranges(ranges(&[(10, 14), (3, 5), (16, 20), (12, 18), (6, 6)]));
+        let merged: Vec<(usize, usize)> = set.iter().map(|r| (r.start, r.end)).collect();
+        assert_eq!(merged, vec![(3, 6), (10, 20)]);
+        assert_eq!(set.len(), 15);
+
+        let set = IntervalSet::from_ranges(ranges(&[(9, 2), (0, 0)]));
+        assert_eq!(set.len(), 1);
+        assert_eq!(IntervalSet::from_ranges(Vec::new()), IntervalSet::default());
+    }
+
+    fn pairs(set: &IntervalSet) -> Vec<(usize, usize)> {
+        set.iter().map(|r| (r.start, r.end)).collect()
+    }
+
+    #[test]
+    fn test_algebra() {
+        let a = IntervalSet::from_ranges(ranges(&[(3, 5), (10, 20)]));
+        let b = IntervalSet::from_ranges(ranges(&[(0, 3), (6, 8), (12, 14), (19, 25)]));
+
+        assert_eq!(pairs(&a.union(&b)), vec![(0, 8), (10, 25)]);
+        assert_eq!(pairs(&a.intersection(&b)), vec![(3, 3), (12, 14), (19, 20)]);
+        assert_eq!(pairs(&a.difference(&b)), vec![(4, 5), (10, 11), (15, 18)]);
+        assert_eq!(pairs(&b.difference(&a)), vec![(0, 2), (6, 8), (21, 25)]);
+        assert_eq!(
+            pairs(&a.symmetric_difference(&b)),
+            vec![(0, 2), (4, 8), (10, 11), (15, 18), (21, 25)]
+        );
+
+        let universe = Range { start: 4, end: 30 };
+        assert_eq!(pairs(&a.complement(universe)), vec![(6, 9), (21, 30)]);
+        let universe = Range { start: 12, end: 15 };
+        assert_eq!(pairs(&a.complement(universe)), Vec::new());
+        let everything = Range {
+            start: 0,
+            end: usize::MAX,
+        };
+        assert_eq!(
+            pairs(&IntervalSet::default().complement(everything)),
+            vec![(0, usize::MAX)]
+        );
+
+        // Brute force over a small universe
+        for id in 0..30 {
+            let (in_a, in_b) = (a.contains(id), b.contains(id));
+            assert_eq!(a.union(&b).contains(id), in_a || in_b);
+            assert_eq!(a.intersection(&b).contains(id), in_a && in_b);
+            assert_eq!(a.difference(&b).contains(id), in_a && !in_b);
+            assert_eq!(a.symmetric_difference(&b).contains(id), in_a != in_b);
+        }
+    }
+
+    #[test]
+    fn test_count_in() {
+        let set = IntervalSet::from_ranges(ranges(&[(3, 5), (10, 14), (16, 20), (12, 18)]));
+        for start in 0..25 {
+            for end in start..25 {
+                let expected = (start..=end).filter(|&id| set.contains(id)).count() as u128;
+                assert_eq!(
+                    set.count_in(Range { start, end }),
+                    expected,
+                    "{start}-{end}"
+                );
+            }
+        }
+        assert_eq!(set.count_in(Range { start: 9, end: 2 }), 0);
+        assert_eq!(
+            IntervalSet::default().count_in(Range { start: 0, end: 9 }),
+            0
+        );
+    }
+
+    #[test]
+    fn test_contains() {
+        let set = IntervalSet::from_ranges(ranges(&[(3, 5), (10, 14), (16, 20), (12, 18)]));
+        for id in 0..25 {
+            let expected = (3..=5).contains(&id) || (10..=20).contains(&id);
+            assert_eq!(set.contains(id), expected, "id {id}");
+        }
+        assert!(!set.contains(usize::MAX));
+
+        let mut sweep = set.sweep();
+        for id in (0..25).step_by(2) {
+            assert_eq!(sweep.contains(id), set.contains(id), "id {id}");
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use crate::Range;
+
+/// A static index over unmerged ranges, each tagged with the database
+/// line it came from, answering which ranges cover an ID.
+///
+/// The ranges are sorted by start and laid out as an implicit balanced
+/// tree: the root of any slice is its midpoint.  Each node also records
+/// the largest end in its subtree, so subtrees that finish before the
+/// query ID are skipped.
+pub struct IntervalTree {
+    entries: Vec<(usize, Range)>,
+    max_end: Vec<usize>,
+}
+
+impl IntervalTree {
+    pub fn new(mut entries: Vec<(usize, Range)>) -> Self {
+        entries.sort_by_key(|&(line, r)| (r.start, line));
+
+        let mut max_end = vec![0; entries.len()];
+        Self::build(&entries, &mut max_end, 0, entries.len());
+
+        Self { entries, max_end }
+    }
+
+    fn build(entries: &[(usize, Range)], max_end: &mut [usize], lo: usize, hi: usize) -> usize {
+        if lo >= hi {
+            return 0;
+        }
+
+        let mid = (lo + hi) / 2;
+        let left = Self::build(entries, max_end, lo, mid);
+        let right = Self::build(entries, max_end, mid + 1, hi);
+        max_end[mid] = entries[mid].1.end.max(left).max(right);
+        max_end[mid]
+    }
+
+    /// Every range covering `id` with its line number, in order of
+    /// start.
+    pub fn stab(&self, id: usize) -> Vec<(usize, Range)> {
+        let mut found = Vec::new();
+        self.stab_in(id, 0, self.entries.len(), &mut found);
+        found
+    }
+
+    fn stab_in(&self, id: usize, lo: usize, hi: usize, found: &mut Vec<(usize, Range)>) {
+        if lo >= hi {
+            return;
+        }
+
+        let mid = (lo + hi) / 2;
+        if self.max_end[mid] < id {
+            return;
+        }
+
+        self.stab_in(id, lo, mid, found);
+
+        // Everything to the right starts at or after this range
+        let (line, range) = self.entries[mid];
+        if range.start <= id {
+            if range.contains(id) {
+                found.push((line, range));
+            }
+            self.stab_in(id, mid + 1, hi, found);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stab() {
+        let entries: Vec<(usize, Range)> = [(3, 5), (10, 14), (16, 20), (12, 18), (4, 4), (0, 30)]
+            .iter()
+            .enumerate()
+            .map(|(i, &(start, end))| (i + 1, Range { start, end }))
+            .collect();
+        let tree = IntervalTree::new(entries.clone());
+
+        for id in 0..35 {
+            let mut expected: Vec<(usize, Range)> = entries
+                .iter()
+                .copied()
+                .filter(|(_, r)| r.contains(id))
+                .collect();
+            expected.sort_by_key(|&(line, r)| (r.start, line));
+            assert_eq!(tree.stab(id), expected, "id {id}");
+        }
+
+        let lines: Vec<usize> = tree.stab(13).iter().map(|&(line, _)| line).collect();
+        assert_eq!(lines, vec![6, 2, 4]);
+        assert!(IntervalTree::new(Vec::new()).stab(0).is_empty());
+    }
+}
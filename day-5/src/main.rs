@@ -1,7 +1,24 @@
+use std::fmt;
 use std::fs::File;
-use std::io::{self, BufRead};
-
-#[derive(Debug, Clone, Copy)]
+use std::io::{self, BufRead, Write};
+
+mod dynamic_set;
+mod gaps;
+mod interval_set;
+mod interval_tree;
+mod snapshot;
+mod timed;
+mod validate;
+
+use dynamic_set::DynamicIntervalSet;
+use gaps::GapReport;
+use interval_set::IntervalSet;
+use interval_tree::IntervalTree;
+use snapshot::Snapshot;
+use timed::TimedRange;
+use validate::Validation;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Range {
     start: usize,
     end: usize,
@@ -11,122 +28,493 @@ impl Range {
     fn contains(&self, id: usize) -> bool {
         id >= self.start && id <= self.end
     }
+
+    /// Number of IDs in the range, widened so `0-usize::MAX` fits.
+    fn size(&self) -> u128 {
+        (self.end - self.start) as u128 + 1
+    }
+
+    /// Parses the `start-end` format used in the database.
+    fn parse(line: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let parts: Vec<&str> = line.split('-').collect();
+        if parts.len() != 2 {
+            return Err(format!("Invalid range format: {}", line).into());
+        }
+
+        let start = parts[0].parse::<usize>()?;
+        let end = parts[1].parse::<usize>()?;
+
+        Ok(Range { start, end })
+    }
 }
 
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+/// A line of the ingredient section: a single ID or a `start-end` batch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ingredient {
+    Id(usize),
+    Batch(Range),
+}
+
+/// Reads a database a line at a time into a buffer that is reused
+/// between lines, so the ingredient section can be streamed however
+/// long it is.
 struct DatabaseReader {
-    lines: io::Lines<io::BufReader<File>>,
+    reader: io::BufReader<File>,
+    buf: String,
+    // number of lines read so far
+    line: usize,
 }
 
 impl DatabaseReader {
     fn new(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
         let file = File::open(path)?;
-        let lines = io::BufReader::new(file).lines();
-        Ok(Self { lines })
+        Ok(Self {
+            reader: io::BufReader::new(file),
+            buf: String::new(),
+            line: 0,
+        })
     }
 
-    fn parse_ranges(&mut self) -> Result<Vec<Range>, Box<dyn std::error::Error>> {
-        let mut ranges = Vec::new();
-
-        for line in &mut self.lines {
-            let line = line?;
-            let line = line.trim();
-
-            // Stop at blank line
-            if line.is_empty() {
-                break;
-            }
+    /// Reads the next line into `buf`, returning false at the end of
+    /// the input.
+    fn read_line(&mut self) -> io::Result<bool> {
+        self.buf.clear();
+        if self.reader.read_line(&mut self.buf)? == 0 {
+            return Ok(false);
+        }
+        self.line += 1;
+        Ok(true)
+    }
 
-            // Parse range format: "start-end"
-            let parts: Vec<&str> = line.split('-').collect();
-            if parts.len() != 2 {
-                return Err(format!("Invalid range format: {}", line).into());
-            }
+    fn parse_ranges(&mut self) -> Result<Vec<Range>, Box<dyn std::error::Error>> {
+        self.parse_ranges_with(Validation::Normal)
+    }
 
-            let start = parts[0].parse::<usize>()?;
-            let end = parts[1].parse::<usize>()?;
+    fn parse_ranges_with(
+        &mut self,
+        validation: Validation,
+    ) -> Result<Vec<Range>, Box<dyn std::error::Error>> {
+        let ranges = self.parse_numbered_ranges_with(validation)?;
+        Ok(ranges.into_iter().map(|(_, range)| range).collect())
+    }
 
-            ranges.push(Range { start, end });
-        }
+    /// Like `parse_ranges`, but keeping the line number of each range.
+    fn parse_numbered_ranges(&mut self) -> Result<Vec<(usize, Range)>, Box<dyn std::error::Error>> {
+        self.parse_numbered_ranges_with(Validation::Normal)
+    }
 
-        Ok(ranges)
+    fn parse_numbered_ranges_with(
+        &mut self,
+        validation: Validation,
+    ) -> Result<Vec<(usize, Range)>, Box<dyn std::error::Error>> {
+        let ranges = self.parse_timed_ranges_with(validation)?;
+        Ok(ranges
+            .into_iter()
+            .map(|(line, timed)| (line, timed.ids))
+            .collect())
     }
 
-    fn parse_ingredient_ids(&mut self) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
-        let mut ids = Vec::new();
+    /// Reads the range section, time windows and all, failing on the
+    /// problems `validation` treats as fatal and skipping lines that
+    /// can't be used.
+    fn parse_timed_ranges_with(
+        &mut self,
+        validation: Validation,
+    ) -> Result<Vec<(usize, TimedRange)>, Box<dyn std::error::Error>> {
+        let checked = self.check_ranges()?;
+        validation.check(&checked.issues)?;
+        Ok(checked.ranges)
+    }
 
-        for line in &mut self.lines {
-            let line = line?;
-            let line = line.trim();
+    /// Returns the next ingredient, skipping blank lines, or `None` at
+    /// the end of the input.
+    fn next_ingredient(&mut self) -> Option<Result<Ingredient, Box<dyn std::error::Error>>> {
+        loop {
+            match self.read_line() {
+                Ok(false) => return None,
+                Ok(true) => {}
+                Err(e) => return Some(Err(e.into())),
+            }
 
+            let line = self.buf.trim();
             if line.is_empty() {
                 continue;
             }
 
-            let id = line.parse::<usize>()?;
-            ids.push(id);
+            let ingredient = if line.contains('-') {
+                Range::parse(line).map(Ingredient::Batch)
+            } else {
+                line.parse::<usize>()
+                    .map(Ingredient::Id)
+                    .map_err(|e| e.into())
+            };
+            return Some(ingredient.map_err(|e| format!("line {}: {e}", self.line).into()));
         }
-
-        Ok(ids)
     }
 }
 
-fn prob1(prob_file: &str) -> Result<usize, Box<dyn std::error::Error>> {
-    let mut reader = DatabaseReader::new(prob_file)?;
-    let ranges = reader.parse_ranges()?;
-    let ingredient_ids = reader.parse_ingredient_ids()?;
+#[derive(Debug, Default)]
+struct Options {
+    // the ingredients are in increasing order
+    sorted: bool,
+    validation: Validation,
+}
 
-    let mut fresh_count = 0;
+/// Streams the ingredient section, counting fresh IDs as they are read,
+/// including every fresh ID inside a batch.
+///
+/// With `sorted`, the ingredients must come in increasing order (batches
+/// by their start) and single IDs are checked by sweeping through the
+/// merged intervals alongside them rather than searching for each.
+fn prob1(prob_file: &str, options: &Options) -> Result<u128, Box<dyn std::error::Error>> {
+    let sorted = options.sorted;
+    let mut reader = DatabaseReader::new(prob_file)?;
+    let fresh = IntervalSet::from_ranges(reader.parse_ranges_with(options.validation)?);
+    let mut sweep = fresh.sweep();
+    let mut last = 0;
+
+    let mut fresh_count: u128 = 0;
+
+    while let Some(ingredient) = reader.next_ingredient() {
+        let ingredient = ingredient?;
+
+        if sorted {
+            let key = match ingredient {
+                Ingredient::Id(id) => id,
+                Ingredient::Batch(range) => range.start,
+            };
+            if key < last {
+                return Err(format!("line {}: ingredients are not sorted", reader.line).into());
+            }
+            last = key;
+        }
 
-    for id in ingredient_ids {
-        let is_fresh = ranges.iter().any(|range| range.contains(id));
-        if is_fresh {
-            fresh_count += 1;
+        match ingredient {
+            Ingredient::Id(id) => {
+                let is_fresh = if sorted {
+                    sweep.contains(id)
+                } else {
+                    fresh.contains(id)
+                };
+                if is_fresh {
+                    fresh_count += 1;
+                }
+            }
+            Ingredient::Batch(range) => fresh_count += fresh.count_in(range),
         }
     }
 
     Ok(fresh_count)
 }
 
-fn prob2(prob_file: &str) -> Result<usize, Box<dyn std::error::Error>> {
+/// The number of fresh IDs in each batch of the ingredient section.
+fn batch_counts(prob_file: &str) -> Result<Vec<(Range, u128)>, Box<dyn std::error::Error>> {
     let mut reader = DatabaseReader::new(prob_file)?;
-    let mut ranges = reader.parse_ranges()?;
-
-    // Sort ranges by start value
-    ranges.sort_by_key(|r| r.start);
-
-    // Merge overlapping or adjacent ranges
-    let mut merged = Vec::new();
-    if let Some(mut current) = ranges.first().copied() {
-        for range in ranges.iter().skip(1) {
-            // If ranges overlap or are adjacent (end + 1 >= start), merge them
-            if current.end >= range.start.saturating_sub(1) {
-                // Merge: extend current range to include this one
-                current.end = current.end.max(range.end);
-            } else {
-                // No overlap: save current and start new
-                merged.push(current);
-                current = *range;
-            }
+    let fresh = IntervalSet::from_ranges(reader.parse_ranges()?);
+
+    let mut counts = Vec::new();
+    while let Some(ingredient) = reader.next_ingredient() {
+        if let Ingredient::Batch(range) = ingredient? {
+            counts.push((range, fresh.count_in(range)));
         }
-        merged.push(current);
     }
 
+    Ok(counts)
+}
+
+fn prob2(prob_file: &str, options: &Options) -> Result<u128, Box<dyn std::error::Error>> {
+    let mut reader = DatabaseReader::new(prob_file)?;
+    let fresh = IntervalSet::from_ranges(reader.parse_ranges_with(options.validation)?);
+
     // Sum up the sizes of all merged ranges
-    let total: usize = merged.iter().map(|r| r.end - r.start + 1).sum();
+    let total = fresh.len();
 
     Ok(total)
 }
 
+/// The merged fresh ranges of a database file.
+fn load_fresh(path: &str) -> Result<IntervalSet, Box<dyn std::error::Error>> {
+    let mut reader = DatabaseReader::new(path)?;
+    Ok(IntervalSet::from_ranges(reader.parse_ranges()?))
+}
+
+/// The fresh ranges of a database file with their time windows.
+fn load_timed(path: &str) -> Result<Vec<TimedRange>, Box<dyn std::error::Error>> {
+    let mut reader = DatabaseReader::new(path)?;
+    let ranges = reader.parse_timed_ranges_with(Validation::Normal)?;
+    Ok(ranges.into_iter().map(|(_, timed)| timed).collect())
+}
+
+/// Counts the ingredient IDs fresh at `time`, and all IDs fresh then.
+fn fresh_at(prob_file: &str, time: usize) -> Result<(u128, u128), Box<dyn std::error::Error>> {
+    let mut reader = DatabaseReader::new(prob_file)?;
+    let ranges: Vec<TimedRange> = reader
+        .parse_timed_ranges_with(Validation::Normal)?
+        .into_iter()
+        .map(|(_, timed)| timed)
+        .collect();
+    let fresh = timed::fresh_at(&ranges, time);
+
+    let mut fresh_count: u128 = 0;
+    while let Some(ingredient) = reader.next_ingredient() {
+        match ingredient? {
+            Ingredient::Id(id) => {
+                if fresh.contains(id) {
+                    fresh_count += 1;
+                }
+            }
+            Ingredient::Batch(range) => fresh_count += fresh.count_in(range),
+        }
+    }
+
+    Ok((fresh_count, fresh.len()))
+}
+
+/// Prints a set one `start-end` range per line, so the output can be
+/// read back as a database.
+fn print_ranges(set: &IntervalSet) -> Result<(), Box<dyn std::error::Error>> {
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    for range in set.iter() {
+        writeln!(out, "{range}")?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// The original range lines that make `id` fresh.
+fn why(prob_file: &str, id: usize) -> Result<Vec<(usize, Range)>, Box<dyn std::error::Error>> {
+    let mut reader = DatabaseReader::new(prob_file)?;
+    let tree = IntervalTree::new(reader.parse_numbered_ranges()?);
+    Ok(tree.stab(id))
+}
+
+/// Replays a log of changes against the fresh ranges of a database.
+/// Each line is `+start-end` to add a range, `-start-end` to retract
+/// one or `?id` to ask whether an ID is fresh; the answers are returned
+/// in order, followed by the final total.
+fn replay(changes_file: &str, prob_file: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut reader = DatabaseReader::new(prob_file)?;
+    let mut fresh = DynamicIntervalSet::new();
+    for range in reader.parse_ranges()? {
+        fresh.insert(range);
+    }
+
+    let mut output = Vec::new();
+    let file = File::open(changes_file)?;
+    for (i, line) in io::BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        let bad_line = |e: Box<dyn std::error::Error>| format!("{changes_file}:{}: {e}", i + 1);
+
+        if let Some(range) = line.strip_prefix('+') {
+            fresh.insert(Range::parse(range).map_err(bad_line)?);
+        } else if let Some(range) = line.strip_prefix('-') {
+            fresh.remove(Range::parse(range).map_err(bad_line)?);
+        } else if let Some(id) = line.strip_prefix('?') {
+            let id: usize = id.parse().map_err(|e| bad_line(Box::new(e)))?;
+            let state = if fresh.contains(id) {
+                "fresh"
+            } else {
+                "spoiled"
+            };
+            output.push(format!("{id}: {state}"));
+        } else if !line.is_empty() {
+            return Err(format!("{changes_file}:{}: Invalid change: {line}", i + 1).into());
+        }
+    }
+
+    output.push(format!("Total fresh ingredient IDs: {}", fresh.len()));
+    Ok(output)
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let input_file = std::env::args()
-        .nth(1)
-        .unwrap_or_else(|| "input.txt".to_string());
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.first().map(String::as_str) {
+        Some(op @ ("union" | "intersection" | "difference" | "symmetric-difference")) => {
+            let (Some(a), Some(b)) = (args.get(1), args.get(2)) else {
+                return Err(format!("usage: day-5 {op} <file> <file>").into());
+            };
+            let (a, b) = (load_fresh(a)?, load_fresh(b)?);
+
+            let result = match op {
+                "union" => a.union(&b),
+                "intersection" => a.intersection(&b),
+                "difference" => a.difference(&b),
+                _ => a.symmetric_difference(&b),
+            };
+            print_ranges(&result)?;
+        }
+        Some("complement") => {
+            let (Some(file), Some(universe)) = (args.get(1), args.get(2)) else {
+                return Err("usage: day-5 complement <file> <start-end>".into());
+            };
+            let universe = Range::parse(universe)?;
+            print_ranges(&load_fresh(file)?.complement(universe))?;
+        }
+        Some("gaps") => {
+            const USAGE: &str =
+                "usage: day-5 gaps [--universe start-end] [--format text|csv] [file]";
+            let mut universe = None;
+            let mut format = "text";
+            let mut input_file = "input.txt";
+            let mut rest = args[1..].iter();
+            while let Some(arg) = rest.next() {
+                match arg.as_str() {
+                    "--universe" => universe = Some(Range::parse(rest.next().ok_or(USAGE)?)?),
+                    "--format" => format = rest.next().ok_or(USAGE)?,
+                    file => input_file = file,
+                }
+            }
+
+            let report = GapReport::new(&load_fresh(input_file)?, universe)?;
+            let mut out = io::BufWriter::new(io::stdout().lock());
+            match format {
+                "text" => report.write_text(&mut out)?,
+                "csv" => report.write_csv(&mut out)?,
+                _ => return Err(format!("Unknown gap format: {format}").into()),
+            }
+            out.flush()?;
+        }
+        Some("at") => {
+            let time: usize = args
+                .get(1)
+                .ok_or("usage: day-5 at <time> [file]")?
+                .parse()?;
+            let input_file = args.get(2).map(String::as_str).unwrap_or("input.txt");
+
+            let (fresh_count, total_fresh) = fresh_at(input_file, time)?;
+            println!("At time {time} - Fresh ingredient IDs: {fresh_count}");
+            println!("At time {time} - Total fresh ingredient IDs: {total_fresh}");
+        }
+        Some("area") => {
+            let input_file = args.get(1).map(String::as_str).unwrap_or("input.txt");
+
+            let ranges = load_timed(input_file)?;
+            println!(
+                "Fresh at any time: {} IDs",
+                timed::fresh_at_any_time(&ranges).len()
+            );
+            println!("Fresh ID x time area: {}", timed::area(&ranges));
+        }
+        Some("snapshot") => {
+            let out_file = args.get(1).ok_or("usage: day-5 snapshot <out> [file]")?;
+            let input_file = args.get(2).map(String::as_str).unwrap_or("input.txt");
+
+            let fresh = load_fresh(input_file)?;
+            let source = snapshot::fingerprint_file(input_file)?;
+            let mut out = io::BufWriter::new(File::create(out_file)?);
+            snapshot::write(&fresh, source, &mut out)?;
+            out.flush()?;
+            println!(
+                "Wrote {} intervals from {input_file} to {out_file}",
+                fresh.iter().len()
+            );
+        }
+        Some("lookup") => {
+            const USAGE: &str = "usage: day-5 lookup <snapshot> [--source file] <id>... \
+                                 (only with --source is a stale snapshot rejected)";
+            let snapshot_file = args.get(1).ok_or(USAGE)?;
+            let mut rest = &args[2..];
+            let mut source = None;
+            if rest.first().map(String::as_str) == Some("--source") {
+                let source_file = rest.get(1).ok_or(USAGE)?;
+                source = Some(snapshot::fingerprint_file(source_file)?);
+                rest = &rest[2..];
+            }
+
+            let snapshot = Snapshot::load(snapshot_file, source)
+                .map_err(|e| format!("{snapshot_file}: {e}"))?;
+            println!("{snapshot_file}: {} intervals", snapshot.intervals());
+            for id in rest {
+                let id: usize = id.parse()?;
+                let state = if snapshot.contains(id) {
+                    "fresh"
+                } else {
+                    "spoiled"
+                };
+                println!("{id}: {state}");
+            }
+        }
+        Some("why") => {
+            let id: usize = args.get(1).ok_or("usage: day-5 why <id> [file]")?.parse()?;
+            let input_file = args.get(2).map(String::as_str).unwrap_or("input.txt");
+
+            let covering = why(input_file, id)?;
+            if covering.is_empty() {
+                println!("{id} is spoiled: no range covers it");
+            } else {
+                println!("{id} is fresh, covered by:");
+                for (line, range) in covering {
+                    println!("  line {line}: {range}");
+                }
+            }
+        }
+        Some("batches") => {
+            let input_file = args.get(1).map(String::as_str).unwrap_or("input.txt");
+            for (range, count) in batch_counts(input_file)? {
+                println!("{range}: {count} fresh");
+            }
+        }
+        Some("replay") => {
+            let changes_file = args.get(1).ok_or("usage: day-5 replay <changes> [file]")?;
+            let input_file = args.get(2).map(String::as_str).unwrap_or("input.txt");
+
+            for line in replay(changes_file, input_file)? {
+                println!("{line}");
+            }
+        }
+        Some("check") => {
+            let input_file = args.get(1).map(String::as_str).unwrap_or("input.txt");
+
+            let issues = DatabaseReader::new(input_file)?.check_ranges()?.issues;
+            for issue in &issues {
+                println!("{issue}");
+            }
+            let errors = issues.iter().filter(|issue| issue.is_error()).count();
+            println!(
+                "{input_file}: {errors} errors, {} warnings",
+                issues.len() - errors
+            );
+        }
+        _ => {
+            let mut options = Options::default();
+            let mut input_file = "input.txt";
+            for arg in &args {
+                match arg.as_str() {
+                    "--sorted" => options.sorted = true,
+                    "--strict" => options.validation = Validation::Strict,
+                    "--lenient" => options.validation = Validation::Lenient,
+                    flag if flag.starts_with("--") => {
+                        return Err(format!(
+                            "Unknown option: {flag}; usage: day-5 [--sorted] [--strict|--lenient] [file]"
+                        )
+                        .into());
+                    }
+                    file => input_file = file,
+                }
+            }
 
-    let fresh_count = prob1(&input_file)?;
-    println!("Part 1 - Fresh ingredient IDs: {}", fresh_count);
+            // Report the range section's problems once, before the
+            // parts parse it quietly
+            let issues = DatabaseReader::new(input_file)?.check_ranges()?.issues;
+            for issue in options.validation.check(&issues)? {
+                eprintln!("{input_file}: {issue}");
+            }
 
-    let total_fresh = prob2(&input_file)?;
-    println!("Part 2 - Total fresh ingredient IDs: {}", total_fresh);
+            let fresh_count = prob1(input_file, &options)?;
+            println!("Part 1 - Fresh ingredient IDs: {}", fresh_count);
+
+            let total_fresh = prob2(input_file, &options)?;
+            println!("Part 2 - Total fresh ingredient IDs: {}", total_fresh);
+        }
+    }
 
     Ok(())
 }
@@ -137,12 +525,109 @@ mod test {
 
     #[test]
     fn check_prob1() {
-        assert_eq!(prob1("sample.txt").unwrap(), 3);
+        assert_eq!(prob1("sample.txt", &Options::default()).unwrap(), 3);
     }
 
     #[test]
     fn check_prob2() {
-        assert_eq!(prob2("sample.txt").unwrap(), 14);
+        assert_eq!(prob2("sample.txt", &Options::default()).unwrap(), 14);
+    }
+
+    #[test]
+    fn check_batches() {
+        assert_eq!(prob1("batches.txt", &Options::default()).unwrap(), 8);
+        assert_eq!(
+            batch_counts("batches.txt").unwrap(),
+            vec![
+                (Range { start: 0, end: 10 }, 4),
+                (Range { start: 15, end: 17 }, 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn check_whole_id_space() {
+        assert_eq!(prob1("everything.txt", &Options::default()).unwrap(), 7);
+        assert_eq!(
+            prob2("everything.txt", &Options::default()).unwrap(),
+            1 << 64
+        );
+        assert_eq!(
+            batch_counts("everything.txt").unwrap(),
+            vec![(Range { start: 5, end: 10 }, 6)]
+        );
+    }
+
+    #[test]
+    fn check_sorted() {
+        let sorted = Options {
+            sorted: true,
+            ..Options::default()
+        };
+        assert_eq!(prob1("sample.txt", &sorted).unwrap(), 3);
+        assert!(prob1("batches.txt", &sorted).is_err());
+        assert_eq!(prob1("sorted.txt", &sorted).unwrap(), 7);
+        assert_eq!(prob1("sorted.txt", &Options::default()).unwrap(), 7);
+    }
+
+    #[test]
+    fn check_validation() {
+        let options = |validation| Options {
+            validation,
+            ..Options::default()
+        };
+        assert!(prob2("invalid.txt", &options(Validation::Normal)).is_err());
+        assert!(prob2("invalid.txt", &options(Validation::Strict)).is_err());
+        assert_eq!(
+            prob2("invalid.txt", &options(Validation::Lenient)).unwrap(),
+            14
+        );
+        assert_eq!(
+            prob1("invalid.txt", &options(Validation::Lenient)).unwrap(),
+            2
+        );
+
+        // Overlaps are fine, even when strict
+        assert_eq!(
+            prob2("sample.txt", &options(Validation::Strict)).unwrap(),
+            14
+        );
+    }
+
+    #[test]
+    fn check_timed() {
+        assert_eq!(fresh_at("timed.txt", 0).unwrap(), (2, 8));
+        assert_eq!(fresh_at("timed.txt", 12).unwrap(), (2, 11));
+        assert_eq!(fresh_at("timed.txt", 30).unwrap(), (1, 5));
+
+        // Fresh at any time, as the windows are ignored
+        assert_eq!(prob1("timed.txt", &Options::default()).unwrap(), 3);
+        assert_eq!(prob2("timed.txt", &Options::default()).unwrap(), 14);
+    }
+
+    #[test]
+    fn check_why() {
+        assert_eq!(
+            why("sample.txt", 17).unwrap(),
+            vec![
+                (4, Range { start: 12, end: 18 }),
+                (3, Range { start: 16, end: 20 })
+            ]
+        );
+        assert!(why("sample.txt", 8).unwrap().is_empty());
+    }
+
+    #[test]
+    fn check_replay() {
+        assert_eq!(
+            replay("changes.txt", "sample.txt").unwrap(),
+            vec![
+                "13: spoiled",
+                "17: fresh",
+                "8: fresh",
+                "Total fresh ingredient IDs: 15"
+            ]
+        );
     }
 
     #[test]
@@ -0,0 +1,324 @@
+use std::fs::File;
+use std::io::{self, BufRead, Read, Write};
+
+use crate::interval_set::IntervalSet;
+
+/// Snapshot layout, all integers little endian:
+///
+/// ```text
+/// magic        8 bytes  "AOC5SNAP"
+/// version      u32
+/// reserved     u32      zero
+/// count        u64      number of intervals
+/// source       u64      fingerprint of the range section it was made from
+/// checksum     u64      FNV-1a of version, reserved, count and source,
+///                       then of everything after the header
+/// starts       count x u64, increasing
+/// ends         count x u64, increasing
+/// ```
+const MAGIC: &[u8; 8] = b"AOC5SNAP";
+const VERSION: u32 = 2;
+const HEADER_LEN: usize = 40;
+
+/// Why a snapshot could not be used.
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    NotASnapshot,
+    UnsupportedVersion(u32),
+    ReservedNotZero(u32),
+    WrongLength { expected: u128, found: usize },
+    ChecksumMismatch { expected: u64, found: u64 },
+    Stale,
+}
+
+impl std::fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotError::Io(e) => write!(f, "{e}"),
+            SnapshotError::NotASnapshot => write!(f, "not a snapshot file"),
+            SnapshotError::UnsupportedVersion(version) => write!(
+                f,
+                "snapshot version {version} is not supported (expected {VERSION})"
+            ),
+            SnapshotError::ReservedNotZero(reserved) => write!(
+                f,
+                "snapshot is corrupt: reserved field is {reserved:08x}, not zero"
+            ),
+            SnapshotError::WrongLength { expected, found } => write!(
+                f,
+                "snapshot is corrupt: expected {expected} bytes, found {found}"
+            ),
+            SnapshotError::ChecksumMismatch { expected, found } => write!(
+                f,
+                "snapshot is corrupt: checksum {found:016x} does not match {expected:016x}"
+            ),
+            SnapshotError::Stale => write!(
+                f,
+                "snapshot is stale: the database's ranges have changed since it was taken"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<io::Error> for SnapshotError {
+    fn from(e: io::Error) -> Self {
+        SnapshotError::Io(e)
+    }
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// Continues a 64-bit FNV-1a hash over more bytes.
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// 64-bit FNV-1a hash.
+pub fn fingerprint(bytes: &[u8]) -> u64 {
+    fnv1a(FNV_OFFSET, bytes)
+}
+
+/// Fingerprints the range section of a database file, to tell whether
+/// a snapshot of it is still current.  The section is hashed a line at
+/// a time, and the ingredient section after it is never read.
+pub fn fingerprint_file(path: &str) -> io::Result<u64> {
+    let mut reader = io::BufReader::new(File::open(path)?);
+    let mut line = Vec::new();
+    let mut hash = FNV_OFFSET;
+
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 || line.trim_ascii().is_empty() {
+            return Ok(hash);
+        }
+        hash = fnv1a(hash, &line);
+    }
+}
+
+/// The checksum of a whole snapshot, covering the header fields after
+/// the magic as well as the intervals.
+fn checksum(buf: &[u8]) -> u64 {
+    fnv1a(fingerprint(&buf[8..32]), &buf[HEADER_LEN..])
+}
+
+/// Writes the merged intervals of `set` as a snapshot of the database
+/// with fingerprint `source`.
+pub fn write(set: &IntervalSet, source: u64, out: &mut impl Write) -> io::Result<()> {
+    let mut buf = Vec::with_capacity(HEADER_LEN + set.iter().len() * 16);
+    buf.extend_from_slice(MAGIC);
+    buf.extend_from_slice(&VERSION.to_le_bytes());
+    buf.extend_from_slice(&0u32.to_le_bytes());
+    buf.extend_from_slice(&(set.iter().len() as u64).to_le_bytes());
+    buf.extend_from_slice(&source.to_le_bytes());
+    // checksum, filled in below
+    buf.extend_from_slice(&0u64.to_le_bytes());
+
+    for range in set.iter() {
+        buf.extend_from_slice(&(range.start as u64).to_le_bytes());
+    }
+    for range in set.iter() {
+        buf.extend_from_slice(&(range.end as u64).to_le_bytes());
+    }
+
+    let checksum = checksum(&buf);
+    buf[32..HEADER_LEN].copy_from_slice(&checksum.to_le_bytes());
+    out.write_all(&buf)
+}
+
+/// A snapshot checked and held in memory, answering queries straight
+/// from its buffer.
+pub struct Snapshot {
+    buf: Vec<u8>,
+    count: usize,
+}
+
+impl Snapshot {
+    /// Loads a snapshot, rejecting it if it is corrupt or, when
+    /// `source` is given, was taken of a different database.
+    pub fn load(path: &str, source: Option<u64>) -> Result<Self, SnapshotError> {
+        let mut buf = Vec::new();
+        File::open(path)?.read_to_end(&mut buf)?;
+        Self::from_bytes(buf, source)
+    }
+
+    pub fn from_bytes(buf: Vec<u8>, source: Option<u64>) -> Result<Self, SnapshotError> {
+        if buf.len() < HEADER_LEN || &buf[..8] != MAGIC {
+            return Err(SnapshotError::NotASnapshot);
+        }
+
+        let word = |offset: usize| u64::from_le_bytes(buf[offset..offset + 8].try_into().unwrap());
+        let version = u32::from_le_bytes(buf[8..12].try_into().unwrap());
+        if version != VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let count = word(16);
+        let expected = HEADER_LEN as u128 + count as u128 * 16;
+        if expected != buf.len() as u128 {
+            return Err(SnapshotError::WrongLength {
+                expected,
+                found: buf.len(),
+            });
+        }
+
+        let checksum = checksum(&buf);
+        if checksum != word(32) {
+            return Err(SnapshotError::ChecksumMismatch {
+                expected: word(32),
+                found: checksum,
+            });
+        }
+        let reserved = u32::from_le_bytes(buf[12..16].try_into().unwrap());
+        if reserved != 0 {
+            return Err(SnapshotError::ReservedNotZero(reserved));
+        }
+        if source.is_some_and(|source| source != word(24)) {
+            return Err(SnapshotError::Stale);
+        }
+
+        Ok(Self {
+            count: count as usize,
+            buf,
+        })
+    }
+
+    fn start(&self, i: usize) -> u64 {
+        let offset = HEADER_LEN + i * 8;
+        u64::from_le_bytes(self.buf[offset..offset + 8].try_into().unwrap())
+    }
+
+    fn end(&self, i: usize) -> u64 {
+        self.start(self.count + i)
+    }
+
+    /// Number of merged intervals in the snapshot.
+    pub fn intervals(&self) -> usize {
+        self.count
+    }
+
+    /// Binary searches the ends for the interval that could hold `id`.
+    pub fn contains(&self, id: usize) -> bool {
+        let id = id as u64;
+        let (mut lo, mut hi) = (0, self.count);
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            if self.end(mid) < id {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        lo < self.count && self.start(lo) <= id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Range;
+
+    fn snapshot_bytes() -> (IntervalSet, Vec<u8>) {
+        let set = IntervalSet::from_ranges(vec![
+            Range { start: 3, end: 5 },
+            Range { start: 10, end: 14 },
+            Range { start: 16, end: 20 },
+            Range { start: 12, end: 18 },
+        ]);
+        let mut bytes = Vec::new();
+        write(&set, 42, &mut bytes).unwrap();
+        (set, bytes)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let (set, bytes) = snapshot_bytes();
+        assert_eq!(bytes.len(), HEADER_LEN + 2 * 16);
+
+        let snapshot = Snapshot::from_bytes(bytes, Some(42)).unwrap();
+        assert_eq!(snapshot.intervals(), 2);
+        for id in 0..25 {
+            assert_eq!(snapshot.contains(id), set.contains(id), "id {id}");
+        }
+
+        let mut empty = Vec::new();
+        write(&IntervalSet::default(), 0, &mut empty).unwrap();
+        assert!(!Snapshot::from_bytes(empty, None).unwrap().contains(0));
+    }
+
+    #[test]
+    fn test_fingerprint_file() {
+        // Only the range section counts
+        assert_eq!(
+            fingerprint_file("sample.txt").unwrap(),
+            fingerprint(b"3-5\n10-14\n16-20\n12-18\n")
+        );
+        assert_eq!(
+            fingerprint_file("sample.txt").unwrap(),
+            fingerprint_file("sorted.txt").unwrap()
+        );
+        assert_ne!(
+            fingerprint_file("sample.txt").unwrap(),
+            fingerprint_file("timed.txt").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_rejects_bad_snapshots() {
+        let (_, bytes) = snapshot_bytes();
+
+        let mut corrupt = bytes.clone();
+        corrupt[HEADER_LEN + 3] ^= 1;
+        assert!(matches!(
+            Snapshot::from_bytes(corrupt, None),
+            Err(SnapshotError::ChecksumMismatch { .. })
+        ));
+
+        let mut truncated = bytes.clone();
+        truncated.pop();
+        assert!(matches!(
+            Snapshot::from_bytes(truncated, None),
+            Err(SnapshotError::WrongLength { .. })
+        ));
+
+        // The header fields are covered by the checksum too
+        for offset in [12, 16, 24] {
+            let mut corrupt = bytes.clone();
+            corrupt[offset] ^= 1;
+            assert!(matches!(
+                Snapshot::from_bytes(corrupt, Some(42)),
+                Err(SnapshotError::ChecksumMismatch { .. } | SnapshotError::WrongLength { .. })
+            ));
+        }
+
+        // Even with a matching checksum, reserved must be zero
+        let mut reserved = bytes.clone();
+        reserved[12] = 1;
+        let resealed = checksum(&reserved);
+        reserved[32..HEADER_LEN].copy_from_slice(&resealed.to_le_bytes());
+        assert!(matches!(
+            Snapshot::from_bytes(reserved, None),
+            Err(SnapshotError::ReservedNotZero(1))
+        ));
+
+        let mut future = bytes.clone();
+        future[8] = 3;
+        assert!(matches!(
+            Snapshot::from_bytes(future, None),
+            Err(SnapshotError::UnsupportedVersion(3))
+        ));
+
+        assert!(matches!(
+            Snapshot::from_bytes(b"3-5\n10-14\n".to_vec(), None),
+            Err(SnapshotError::NotASnapshot)
+        ));
+        assert!(matches!(
+            Snapshot::from_bytes(bytes, Some(7)),
+            Err(SnapshotError::Stale)
+        ));
+    }
+}
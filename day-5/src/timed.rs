@@ -0,0 +1,204 @@
+use crate::Range;
+use crate::interval_set::IntervalSet;
+
+/// A range of IDs that is fresh only during a window of time, written
+/// `start-end@t0-t1`.  Without the suffix it is fresh at every time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimedRange {
+    pub ids: Range,
+    pub window: Option<Range>,
+}
+
+impl TimedRange {
+    pub fn parse(line: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let Some((ids, window)) = line.split_once('@') else {
+            return Ok(Self {
+                ids: Range::parse(line)?,
+                window: None,
+            });
+        };
+
+        let window = Range::parse(window)?;
+        if window.start > window.end {
+            return Err(format!("Time window {window} ends before it starts").into());
+        }
+
+        Ok(Self {
+            ids: Range::parse(ids)?,
+            window: Some(window),
+        })
+    }
+
+    pub fn is_fresh_at(&self, time: usize) -> bool {
+        self.window.is_none_or(|window| window.contains(time))
+    }
+}
+
+/// The IDs that are fresh at `time`.
+pub fn fresh_at(ranges: &[TimedRange], time: usize) -> IntervalSet {
+    IntervalSet::from_ranges(
+        ranges
+            .iter()
+            .filter(|timed| timed.is_fresh_at(time))
+            .map(|timed| timed.ids)
+            .collect(),
+    )
+}
+
+/// The IDs that are fresh at some time.
+pub fn fresh_at_any_time(ranges: &[TimedRange]) -> IntervalSet {
+    IntervalSet::from_ranges(ranges.iter().map(|timed| timed.ids).collect())
+}
+
+/// The number of (ID, time) pairs that are fresh, counting the overlap
+/// of several ranges once.  A range without a window covers every time
+/// from 0 to `usize::MAX`; the result saturates at `u128::MAX`.
+///
+/// Sweeps through time, keeping the length of the union of the active
+/// ID ranges in a segment tree over the compressed ID boundaries.
+pub fn area(ranges: &[TimedRange]) -> u128 {
+    // Half-open [start, end) coordinates, widened so end + 1 can't
+    // overflow
+    let rects: Vec<[u128; 4]> = ranges
+        .iter()
+        .filter(|timed| timed.ids.start <= timed.ids.end)
+        .map(|timed| {
+            let window = timed.window.unwrap_or(Range {
+                start: 0,
+                end: usize::MAX,
+            });
+            [
+                timed.ids.start as u128,
+                timed.ids.end as u128 + 1,
+                window.start as u128,
+                window.end as u128 + 1,
+            ]
+        })
+        .collect();
+
+    let mut xs: Vec<u128> = rects.iter().flat_map(|r| [r[0], r[1]]).collect();
+    xs.sort();
+    xs.dedup();
+    if xs.len() < 2 {
+        return 0;
+    }
+
+    // (time, change in cover, first and last+1 elementary ID interval)
+    let mut events: Vec<(u128, i32, usize, usize)> = Vec::with_capacity(rects.len() * 2);
+    for &[x0, x1, t0, t1] in &rects {
+        let from = xs.partition_point(|&x| x < x0);
+        let to = xs.partition_point(|&x| x < x1);
+        events.push((t0, 1, from, to));
+        events.push((t1, -1, from, to));
+    }
+    events.sort();
+
+    let mut tree = CoverTree::new(xs);
+    let mut total: u128 = 0;
+    let mut last_time = 0;
+    for (time, delta, from, to) in events {
+        let covered = tree.covered[1];
+        total = total.saturating_add(covered.saturating_mul(time - last_time));
+        tree.update(1, 0, tree.xs.len() - 1, from, to, delta);
+        last_time = time;
+    }
+
+    total
+}
+
+/// Segment tree over the elementary intervals between sorted
+/// boundaries, tracking how much of them is covered at least once.
+struct CoverTree {
+    xs: Vec<u128>,
+    // number of ranges covering the whole of a node
+    count: Vec<i32>,
+    // covered length within a node
+    covered: Vec<u128>,
+}
+
+impl CoverTree {
+    fn new(xs: Vec<u128>) -> Self {
+        let nodes = 4 * xs.len();
+        Self {
+            xs,
+            count: vec![0; nodes],
+            covered: vec![0; nodes],
+        }
+    }
+
+    /// Adds `delta` to the cover of elementary intervals [from, to)
+    /// within node `node`, which spans [lo, hi).
+    fn update(&mut self, node: usize, lo: usize, hi: usize, from: usize, to: usize, delta: i32) {
+        if to <= lo || hi <= from {
+            return;
+        }
+
+        if from <= lo && hi <= to {
+            self.count[node] += delta;
+        } else {
+            let mid = (lo + hi) / 2;
+            self.update(2 * node, lo, mid, from, to, delta);
+            self.update(2 * node + 1, mid, hi, from, to, delta);
+        }
+
+        self.covered[node] = if self.count[node] > 0 {
+            self.xs[hi] - self.xs[lo]
+        } else if hi - lo == 1 {
+            0
+        } else {
+            self.covered[2 * node] + self.covered[2 * node + 1]
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timed(line: &str) -> TimedRange {
+        TimedRange::parse(line).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            timed("3-5@10-20"),
+            TimedRange {
+                ids: Range { start: 3, end: 5 },
+                window: Some(Range { start: 10, end: 20 })
+            }
+        );
+        assert_eq!(timed("3-5").window, None);
+        assert!(TimedRange::parse("3-5@20-10").is_err());
+        assert!(TimedRange::parse("3-5@").is_err());
+    }
+
+    #[test]
+    fn test_area() {
+        let ranges: Vec<TimedRange> = ["0-9@0-9", "5-14@5-14", "20-20@3-3", "30-25@0-5"]
+            .iter()
+            .map(|line| timed(line))
+            .collect();
+        // two overlapping 10x10 squares sharing a 5x5 corner, plus a
+        // single cell; the reversed range is empty
+        assert_eq!(area(&ranges), 100 + 100 - 25 + 1);
+
+        // Brute force over the grid
+        let cells = (0..40)
+            .flat_map(|id| (0..40).map(move |time| (id, time)))
+            .filter(|&(id, time)| {
+                ranges
+                    .iter()
+                    .any(|r| r.ids.contains(id) && r.is_fresh_at(time))
+            })
+            .count();
+        assert_eq!(area(&ranges), cells as u128);
+
+        assert_eq!(area(&[timed("1-1")]), 1 << 64);
+        assert_eq!(area(&[]), 0);
+
+        assert_eq!(fresh_at(&ranges, 12).len(), 10);
+        assert_eq!(fresh_at(&ranges, 3).len(), 11);
+        assert_eq!(fresh_at_any_time(&ranges).len(), 16);
+    }
+}
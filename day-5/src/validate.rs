@@ -0,0 +1,221 @@
+use std::fmt;
+use std::io;
+
+use crate::timed::TimedRange;
+use crate::{DatabaseReader, Range};
+
+/// How problems found in the range section are treated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Validation {
+    /// Every problem, warnings included, is fatal.
+    Strict,
+    /// Errors are fatal; warnings are reported.
+    #[default]
+    Normal,
+    /// Nothing is fatal: lines with errors are reported and skipped.
+    Lenient,
+}
+
+impl Validation {
+    pub fn is_fatal(&self, issue: &Issue) -> bool {
+        match self {
+            Validation::Strict => true,
+            Validation::Normal => issue.is_error(),
+            Validation::Lenient => false,
+        }
+    }
+
+    /// Fails with every issue this mode treats as fatal, or returns the
+    /// rest for reporting.
+    pub fn check<'a>(&self, issues: &'a [Issue]) -> Result<Vec<&'a Issue>, String> {
+        let (fatal, rest): (Vec<&Issue>, Vec<&Issue>) =
+            issues.iter().partition(|issue| self.is_fatal(issue));
+        if fatal.is_empty() {
+            return Ok(rest);
+        }
+
+        let fatal: Vec<String> = fatal.iter().map(|issue| issue.to_string()).collect();
+        Err(fatal.join("; "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// The line is not `start-end`.
+    Malformed(String),
+    /// The range ends before it starts.
+    Reversed(Range),
+    /// The same range appears on an earlier line.
+    Duplicate { range: Range, of: usize },
+    /// Another line's range covers this one at every time.
+    Contained { range: Range, within: usize },
+    /// There are no usable ranges at all.
+    Empty,
+}
+
+/// A problem and the line it is on, if it belongs to one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub line: Option<usize>,
+    pub problem: Problem,
+}
+
+impl Issue {
+    /// Errors make a line unusable; warnings leave a usable database.
+    pub fn is_error(&self) -> bool {
+        matches!(self.problem, Problem::Malformed(_) | Problem::Reversed(_))
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {line}: ")?;
+        }
+        let severity = if self.is_error() { "error" } else { "warning" };
+        write!(f, "{severity}: ")?;
+
+        match &self.problem {
+            Problem::Malformed(e) => write!(f, "{e}"),
+            Problem::Reversed(range) => write!(f, "{range} ends before it starts"),
+            Problem::Duplicate { range, of } => write!(f, "{range} duplicates line {of}"),
+            Problem::Contained { range, within } => {
+                write!(f, "{range} is contained in line {within}")
+            }
+            Problem::Empty => write!(f, "the database has no fresh ranges"),
+        }
+    }
+}
+
+/// The usable ranges of a database with their line numbers, and every
+/// problem found, in line order.
+#[derive(Debug)]
+pub struct CheckedRanges {
+    pub ranges: Vec<(usize, TimedRange)>,
+    pub issues: Vec<Issue>,
+}
+
+impl DatabaseReader {
+    /// Reads the range section, noting problems rather than failing on
+    /// them.
+    pub fn check_ranges(&mut self) -> io::Result<CheckedRanges> {
+        let mut ranges = Vec::new();
+        let mut issues = Vec::new();
+
+        while self.read_line()? {
+            let line = self.buf.trim();
+
+            // Stop at blank line
+            if line.is_empty() {
+                break;
+            }
+
+            let problem = match TimedRange::parse(line) {
+                Ok(timed) if timed.ids.start > timed.ids.end => Problem::Reversed(timed.ids),
+                Ok(timed) => {
+                    ranges.push((self.line, timed));
+                    continue;
+                }
+                Err(e) => Problem::Malformed(e.to_string()),
+            };
+            issues.push(Issue {
+                line: Some(self.line),
+                problem,
+            });
+        }
+
+        if ranges.is_empty() {
+            issues.push(Issue {
+                line: None,
+                problem: Problem::Empty,
+            });
+        }
+
+        // Visit by start, longest first and ranges without a window
+        // before those with one, so anything covering a range is seen
+        // before it and duplicates are next to each other
+        let mut order = ranges.clone();
+        order.sort_by_key(|&(line, timed)| {
+            let window = timed.window.map(|w| (w.start, w.end));
+            let ids = timed.ids;
+            (ids.start, std::cmp::Reverse(ids.end), window, line)
+        });
+
+        // Only a range fresh at every time can cover another
+        let mut widest: Option<(usize, Range)> = None;
+        let mut previous: Option<(usize, TimedRange)> = None;
+        for (line, timed) in order {
+            let range = timed.ids;
+            if let Some((of, p)) = previous
+                && p == timed
+            {
+                issues.push(Issue {
+                    line: Some(line),
+                    problem: Problem::Duplicate { range, of },
+                });
+                continue;
+            }
+            previous = Some((line, timed));
+
+            match widest {
+                Some((within, r)) if r.end >= range.end => issues.push(Issue {
+                    line: Some(line),
+                    problem: Problem::Contained { range, within },
+                }),
+                _ if timed.window.is_none() => widest = Some((line, range)),
+                _ => {}
+            }
+        }
+
+        issues.sort_by_key(|issue| issue.line);
+        Ok(CheckedRanges { ranges, issues })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_ranges() {
+        let mut reader = DatabaseReader::new("invalid.txt").unwrap();
+        let CheckedRanges { ranges, issues } = reader.check_ranges().unwrap();
+
+        let lines: Vec<usize> = ranges.iter().map(|&(line, _)| line).collect();
+        assert_eq!(lines, vec![1, 3, 4, 5, 6]);
+
+        let messages: Vec<String> = issues.iter().map(Issue::to_string).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "line 2: error: 10-3 ends before it starts",
+                "line 4: warning: 3-5 duplicates line 1",
+                "line 5: warning: 12-14 is contained in line 3",
+                "line 6: warning: 16-18 is contained in line 3",
+                "line 7: error: invalid digit found in string",
+            ]
+        );
+
+        let errors = issues.iter().filter(|issue| issue.is_error()).count();
+        assert_eq!(errors, 2);
+        assert!(Validation::Strict.is_fatal(&issues[1]));
+        assert!(!Validation::Normal.is_fatal(&issues[1]));
+        assert!(Validation::Normal.is_fatal(&issues[0]));
+        assert!(!Validation::Lenient.is_fatal(&issues[0]));
+
+        assert_eq!(Validation::Lenient.check(&issues).unwrap().len(), 5);
+        assert_eq!(
+            Validation::Normal.check(&issues).unwrap_err(),
+            "line 2: error: 10-3 ends before it starts; \
+             line 7: error: invalid digit found in string"
+        );
+        assert_eq!(
+            Validation::Strict
+                .check(&issues)
+                .unwrap_err()
+                .matches("; ")
+                .count(),
+            4
+        );
+    }
+}